@@ -1,5 +1,22 @@
 #[allow(clippy::new_without_default)]
 mod tomasulo;
 
+pub use tomasulo::cache::{CacheConfig, Mshr, Replacement, WritePolicy};
+pub use tomasulo::cdb::CdbPolicy;
+pub use tomasulo::config::{ConfigError, MachineConfig};
+pub use tomasulo::exception::{Exception, Fault};
 pub use tomasulo::executer;
-pub use tomasulo::inst::Instruction;
+pub use tomasulo::inst::{Instruction, Type};
+pub use tomasulo::lsq::LsqPolicy;
+pub use tomasulo::memory::Memory;
+pub use tomasulo::parser::{parse_program, parse_program_with, ParseError, Span};
+pub use tomasulo::predictor::{Predictor, PredictorKind};
+pub use tomasulo::program::Program;
+pub use tomasulo::render;
+pub use tomasulo::report::{CycleReport, Event};
+pub use tomasulo::stats::{Stall, Stats};
+pub use tomasulo::units::{
+    DispatchPolicy, EuId, EuInner, EuType, FloatingUnit, FuId, IntegerUnit, RegId, RobEntry, RobId,
+    RobState, RsId, RsInner, RsState, RsType, Tag, Unit,
+};
+pub use tomasulo::value::{EvalMode, Value, ValueInner};
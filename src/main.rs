@@ -1,15 +1,37 @@
-use tomasulo_sim::{executer, Instruction};
+use std::io::{Read, Write};
+use std::process::ExitCode;
 
-fn main() {
-    let insts = [
-        r"
+use tomasulo_sim::render::{ascii, csv, json, kanata, latex, markdown, o3, svg, ConsoleRenderer};
+use tomasulo_sim::{executer, parse_program, parse_program_with, MachineConfig};
+
+const USAGE: &str = "\
+Usage: tomasulo-sim [COMMAND]
+
+Commands:
+  run [OPTIONS] [FILE]  Simulate the program in FILE, or stdin if FILE is `-` or missing
+  demo                  Simulate the built-in example programs (default)
+  help                  Print this message
+
+Options:
+  --config <FILE>       Load the machine configuration from FILE
+  --max-cycles <N>      Stop with an error after N cycles
+  --format <FORMAT>     Output format: table (default), json, csv,
+                        markdown, ascii, latex, kanata, o3, svg or trace
+  --cycles <N,..>       With latex, print the tables of these cycles
+                        instead of the last one
+  --quiet               Only print the final instruction timing
+  --only-final          Only print the state of the last cycle
+  --stats               Print statistics of the run at the end";
+
+const DEMO: [&str; 2] = [
+    r"
         LD F6 34+ R2
         LD F2 45+ R3
         MULTD F0 F2 F4
         SUBD F8 F6 F2
         DIVD F10 F0 F6
         ADDD F6 F8 F2",
-        r"
+    r"
         LD F2 0 R2
         LD F4 0 R3
         DIVD F0 F4 F2
@@ -18,21 +40,240 @@ fn main() {
         SD F6 0 R3
         MULTD F6 F0 F2
         SD F6 0 R1",
-    ];
-
-    let parsed_insts = insts
-        .iter()
-        .map(|s| {
-            s.lines()
-                .filter(|s| !s.is_empty())
-                .map(|s| s.parse::<Instruction>().unwrap())
-                .collect::<Vec<_>>()
-        })
-        .collect::<Vec<_>>();
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Table,
+    Json,
+    Csv,
+    Markdown,
+    Ascii,
+    Latex,
+    Kanata,
+    O3,
+    Svg,
+    Trace,
+}
 
-    for insts in parsed_insts.iter() {
-        let mut executer = executer::Executer::new();
-        executer.add_insts(insts);
-        executer.run();
+#[derive(Debug)]
+struct Options {
+    file: Option<String>,
+    config: Option<String>,
+    max_cycles: Option<u64>,
+    format: Format,
+    cycles: Vec<u64>,
+    quiet: bool,
+    only_final: bool,
+    stats: bool,
+}
+
+enum Command {
+    Run(Options),
+    Demo,
+    Help,
+}
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let command = match parse_args(&args) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("error: {message}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match command {
+        Command::Help => {
+            println!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        Command::Demo => {
+            for program in DEMO {
+                let program = parse_program(program).expect("the demo programs are valid");
+                let mut executer = executer::Executer::new(MachineConfig::default())
+                    .expect("the default machine is valid");
+                executer
+                    .load(&program)
+                    .expect("the demo programs are valid");
+                if let Err(e) = executer.run() {
+                    eprintln!("error: {e}");
+                    return ExitCode::FAILURE;
+                }
+            }
+            ExitCode::SUCCESS
+        }
+        Command::Run(options) => match run(&options) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(message) => {
+                eprintln!("error: {message}");
+                ExitCode::FAILURE
+            }
+        },
     }
 }
+
+fn parse_args(args: &[String]) -> Result<Command, String> {
+    let Some((command, args)) = args.split_first() else {
+        return Ok(Command::Demo);
+    };
+    match command.as_str() {
+        "run" => {}
+        "demo" if args.is_empty() => return Ok(Command::Demo),
+        "help" | "--help" | "-h" => return Ok(Command::Help),
+        "demo" => return Err(String::from("`demo` takes no arguments")),
+        _ => return Err(format!("unknown command `{command}`")),
+    }
+
+    let mut options = Options {
+        file: None,
+        config: None,
+        max_cycles: None,
+        format: Format::Table,
+        cycles: Vec::new(),
+        quiet: false,
+        only_final: false,
+        stats: false,
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("`{name}` expects a value"))
+        };
+        match arg.as_str() {
+            "--config" => options.config = Some(value(arg)?),
+            "--max-cycles" => {
+                let n = value(arg)?;
+                options.max_cycles = match n.parse() {
+                    Ok(0) => return Err(String::from("`--max-cycles` must be at least 1")),
+                    Ok(cycles) => Some(cycles),
+                    Err(_) => return Err(format!("invalid number of cycles `{n}`")),
+                };
+            }
+            "--format" => {
+                options.format = match value(arg)?.as_str() {
+                    "table" => Format::Table,
+                    "json" => Format::Json,
+                    "csv" => Format::Csv,
+                    "markdown" => Format::Markdown,
+                    "ascii" => Format::Ascii,
+                    "latex" => Format::Latex,
+                    "kanata" => Format::Kanata,
+                    "o3" => Format::O3,
+                    "svg" => Format::Svg,
+                    "trace" => Format::Trace,
+                    format => return Err(format!("unknown format `{format}`")),
+                }
+            }
+            "--cycles" => {
+                for n in value(arg)?.split(',') {
+                    let n = n.trim();
+                    options
+                        .cycles
+                        .push(n.parse().map_err(|_| format!("invalid cycle `{n}`"))?);
+                }
+            }
+            "--quiet" => options.quiet = true,
+            "--only-final" => options.only_final = true,
+            "--stats" => options.stats = true,
+            "-" => options.file = None,
+            flag if flag.starts_with("--") => return Err(format!("unknown option `{flag}`")),
+            file if options.file.is_none() => options.file = Some(file.to_string()),
+            file => return Err(format!("unexpected argument `{file}`")),
+        }
+    }
+    if !options.cycles.is_empty() && options.format != Format::Latex {
+        return Err(String::from(
+            "`--cycles` is only used with `--format latex`",
+        ));
+    }
+    Ok(Command::Run(options))
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let mut config = match options.config.as_ref() {
+        Some(path) => MachineConfig::load(path).map_err(|e| format!("{path}: {e}"))?,
+        None => MachineConfig::default(),
+    };
+    if let Some(cycles) = options.max_cycles {
+        config = config.with_max_cycles(cycles);
+    }
+
+    let source = match options.file.as_ref() {
+        Some(path) => std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?,
+        None => {
+            let mut source = String::new();
+            std::io::stdin()
+                .read_to_string(&mut source)
+                .map_err(|e| format!("stdin: {e}"))?;
+            source
+        }
+    };
+    let program = parse_program_with(&source, &config).map_err(|errors| {
+        let snippets = errors.iter().map(|e| e.snippet(&source));
+        let count = match errors.len() {
+            1 => String::from("1 error"),
+            n => format!("{n} errors"),
+        };
+        format!(
+            "could not parse the program due to {count}\n\n{}",
+            snippets.collect::<Vec<_>>().join("\n")
+        )
+    })?;
+
+    let mut executer = executer::Executer::new(config).map_err(|e| e.to_string())?;
+    executer.load(&program).map_err(|e| e.to_string())?;
+
+    let mut renderer = ConsoleRenderer::new(std::io::stdout());
+    let every_cycle = options.format == Format::Table && !options.quiet && !options.only_final;
+    // only take a snapshot of the cycles which are printed
+    let snapshots = every_cycle
+        || options.format == Format::Trace
+        || (options.format == Format::Latex && !options.cycles.is_empty());
+    let mut report = executer.report();
+    while !executer.finished {
+        if !snapshots {
+            if let Some(last) = executer.advance().map_err(|e| e.to_string())? {
+                report = last;
+            }
+            continue;
+        }
+        report = executer.step().map_err(|e| e.to_string())?;
+        if every_cycle {
+            renderer.render(&report).map_err(|e| e.to_string())?;
+        }
+        if options.format == Format::Trace {
+            json::write_cycle(&mut std::io::stdout(), &report).map_err(|e| e.to_string())?;
+        }
+        if options.format == Format::Latex && options.cycles.contains(&report.cycle) {
+            latex::write_cycle(&mut std::io::stdout(), &report).map_err(|e| e.to_string())?;
+        }
+    }
+
+    let result = match (options.format, options.quiet) {
+        (Format::Table, true) => renderer.render_insts(&report),
+        (Format::Table, false) if options.only_final => renderer.render(&report),
+        (Format::Table, false) => Ok(()),
+        (Format::Json, _) => json::write_insts(&mut std::io::stdout(), &report),
+        (Format::Csv, _) => csv::write_insts(&mut std::io::stdout(), &report),
+        (Format::Markdown, _) => markdown::write_insts(&mut std::io::stdout(), &report),
+        (Format::Ascii, _) => ascii::write_insts(&mut std::io::stdout(), &report),
+        (Format::Latex, _) if options.cycles.is_empty() => {
+            latex::write_cycle(&mut std::io::stdout(), &report)
+        }
+        (Format::Kanata, _) => kanata::write_insts(&mut std::io::stdout(), &report),
+        (Format::O3, _) => o3::write_insts(&mut std::io::stdout(), &report),
+        (Format::Svg, _) => svg::write_insts(&mut std::io::stdout(), &report),
+        (Format::Latex | Format::Trace, _) => Ok(()),
+    };
+    result
+        .and_then(|_| match options.stats {
+            true => writeln!(std::io::stdout(), "{}", executer.stats),
+            false => Ok(()),
+        })
+        .and_then(|_| std::io::stdout().flush())
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,388 @@
+use std::str::FromStr;
+
+/// Which block of a set is evicted on a miss.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Replacement {
+    /// The least recently used block.
+    #[default]
+    Lru,
+    /// The block loaded first.
+    Fifo,
+    /// A pseudo-random block, the same from run to run.
+    Random,
+}
+
+/// When stores reach the memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WritePolicy {
+    /// Stores allocate the block and mark it dirty; dirty blocks are
+    /// written to the memory when they are evicted.
+    #[default]
+    WriteBack,
+    /// Stores are written to the memory at once and do not allocate.
+    WriteThrough,
+}
+
+/// The description of the data cache.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheConfig {
+    /// The capacity in bytes.
+    pub size: usize,
+    /// The number of blocks per set.
+    pub assoc: usize,
+    /// The block size in bytes.
+    pub block: usize,
+    pub replacement: Replacement,
+    pub write_policy: WritePolicy,
+    /// The cycles added to a load which misses.
+    pub miss_penalty: u64,
+    /// The number of miss status holding registers, `None` if misses
+    /// are not tracked and each one takes the whole penalty.
+    pub mshrs: Option<usize>,
+}
+
+/// The outcome of an access to the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheAccess {
+    pub hit: bool,
+    /// Whether the access writes the memory, i.e. a store written
+    /// through or a dirty block written back.
+    pub mem_write: bool,
+    /// Whether the miss merged into the outstanding miss of its block.
+    pub merged: bool,
+}
+
+/// A miss status holding register, tracking a block being loaded.
+#[derive(Debug, Clone)]
+pub struct Mshr {
+    pub block: i64,
+    /// The cycle the block arrives.
+    pub ready: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    tag: i64,
+    dirty: bool,
+    /// When the block was loaded, for FIFO.
+    loaded: u64,
+    /// When the block was last used, for LRU.
+    used: u64,
+    /// The cycle the block arrives from memory. Until then accesses
+    /// to it merge into its miss.
+    ready: u64,
+}
+
+/// A set-associative data cache.
+///
+/// Only the tags are kept: the values stay in `Memory`, the cache
+/// only decides how long an access takes.
+///
+/// A block is in the cache from its miss on, but only arrives after
+/// the miss penalty: accesses to it before that merge into the miss
+/// and wait for the block.
+///
+/// With MSHRs the cache is non-blocking: each miss to a new block
+/// takes a register until the block arrives, and later misses to
+/// the same block merge into it and only wait for the block. A load
+/// which misses when every register is taken can not start.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    config: CacheConfig,
+    sets: Vec<Vec<Line>>,
+    mshrs: Vec<Mshr>,
+    clock: u64,
+    seed: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> CacheConfig {
+        CacheConfig {
+            size: 1024,
+            assoc: 2,
+            block: 32,
+            replacement: Replacement::Lru,
+            write_policy: WritePolicy::WriteBack,
+            miss_penalty: 10,
+            mshrs: None,
+        }
+    }
+}
+
+impl Cache {
+    /// Build an empty cache. The size must be a multiple of the block
+    /// size times the associativity, as [`MachineConfig::validate`]
+    /// checks.
+    pub fn new(config: CacheConfig) -> Cache {
+        let sets = config.size / (config.block * config.assoc);
+        Cache {
+            sets: vec![Vec::with_capacity(config.assoc); sets],
+            mshrs: Vec::new(),
+            config,
+            clock: 0,
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    #[inline]
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    /// Iterate over the outstanding misses.
+    pub fn mshrs(&self) -> impl Iterator<Item = &Mshr> {
+        self.mshrs.iter()
+    }
+
+    /// The number of free MSHRs in a cycle, `None` if they are unlimited.
+    pub fn free_mshrs(&self, cycle: u64) -> Option<usize> {
+        let busy = self.mshrs.iter().filter(|mshr| mshr.ready > cycle).count();
+        self.config.mshrs.map(|count| count.saturating_sub(busy))
+    }
+
+    /// The block a read of `addr` in `cycle` would take an MSHR for,
+    /// `None` if it hits or merges into an outstanding miss.
+    pub fn primary_miss(&self, addr: i64, cycle: u64) -> Option<i64> {
+        let (block, index, tag) = self.locate(addr);
+        let pending = self
+            .mshrs
+            .iter()
+            .any(|mshr| mshr.block == block && mshr.ready > cycle);
+        let hit = self.sets[index].iter().any(|line| line.tag == tag);
+        (!pending && !hit).then_some(block)
+    }
+
+    /// Read the block of an address in `cycle`, loading it on a miss.
+    ///
+    /// Returns the access and the cycles it adds to the load: the miss
+    /// penalty on a miss, twice if a dirty block is written back first,
+    /// or the cycles left until the block arrives for a merged miss.
+    pub fn read(&mut self, addr: i64, cycle: u64) -> (CacheAccess, u64) {
+        let (block, index, tag) = self.locate(addr);
+        self.mshrs.retain(|mshr| mshr.ready > cycle);
+        if let Some(mshr) = self.mshrs.iter().find(|mshr| mshr.block == block) {
+            let access = CacheAccess {
+                hit: false,
+                mem_write: false,
+                merged: true,
+            };
+            return (access, mshr.ready - cycle);
+        }
+
+        let access = self.access(addr, false, cycle);
+        let line = self.sets[index].iter_mut().find(|line| line.tag == tag);
+        let line = line.expect("a read allocates its block");
+        let mut penalty = match access {
+            CacheAccess { hit: true, .. } => 0,
+            CacheAccess { merged: true, .. } => return (access, line.ready - cycle),
+            CacheAccess {
+                mem_write: true, ..
+            } => 2 * self.config.miss_penalty,
+            _ => self.config.miss_penalty,
+        };
+        if let (false, Some(count)) = (access.hit, self.config.mshrs) {
+            // with every register taken, the misses get them in the
+            // order they free up
+            let mut ready = self.mshrs.iter().map(|mshr| mshr.ready).collect::<Vec<_>>();
+            ready.sort_unstable();
+            if ready.len() >= count {
+                penalty += ready[ready.len() - count] - cycle;
+            }
+            self.mshrs.push(Mshr {
+                block,
+                ready: cycle + penalty,
+            });
+        }
+        line.ready = cycle + penalty;
+        (access, penalty)
+    }
+
+    /// The block, set index and tag of an address.
+    fn locate(&self, addr: i64) -> (i64, usize, i64) {
+        let block = addr.div_euclid(self.config.block as i64);
+        let index = block.rem_euclid(self.sets.len() as i64) as usize;
+        let tag = block.div_euclid(self.sets.len() as i64);
+        (block, index, tag)
+    }
+
+    /// Write an address in `cycle` when a store writes the memory.
+    pub fn write(&mut self, addr: i64, cycle: u64) -> CacheAccess {
+        self.access(addr, true, cycle)
+    }
+
+    fn access(&mut self, addr: i64, write: bool, cycle: u64) -> CacheAccess {
+        self.clock += 1;
+        let (_, index, tag) = self.locate(addr);
+        let through = self.config.write_policy == WritePolicy::WriteThrough;
+
+        if let Some(line) = self.sets[index].iter_mut().find(|line| line.tag == tag) {
+            line.used = self.clock;
+            line.dirty |= write && !through;
+            // the block is still on its way
+            let pending = line.ready > cycle;
+            return CacheAccess {
+                hit: !pending,
+                mem_write: write && through,
+                merged: pending,
+            };
+        }
+        if write && through {
+            return CacheAccess {
+                hit: false,
+                mem_write: true,
+                merged: false,
+            };
+        }
+
+        let line = Line {
+            tag,
+            dirty: write,
+            loaded: self.clock,
+            used: self.clock,
+            ready: cycle,
+        };
+        let victim = self.victim(index);
+        let set = &mut self.sets[index];
+        let mut mem_write = false;
+        match victim {
+            Some(i) => mem_write = std::mem::replace(&mut set[i], line).dirty,
+            None => set.push(line),
+        }
+        CacheAccess {
+            hit: false,
+            mem_write,
+            merged: false,
+        }
+    }
+
+    /// The block of a set to evict, `None` if the set is not full.
+    fn victim(&mut self, index: usize) -> Option<usize> {
+        let set = &self.sets[index];
+        if set.len() < self.config.assoc {
+            return None;
+        }
+        let oldest =
+            |key: fn(&Line) -> u64| (0..set.len()).min_by_key(|&i| key(&set[i])).unwrap_or(0);
+        Some(match self.config.replacement {
+            Replacement::Lru => oldest(|line| line.used),
+            Replacement::Fifo => oldest(|line| line.loaded),
+            Replacement::Random => {
+                // xorshift, so that runs can be reproduced
+                self.seed ^= self.seed << 13;
+                self.seed ^= self.seed >> 7;
+                self.seed ^= self.seed << 17;
+                (self.seed % set.len() as u64) as usize
+            }
+        })
+    }
+
+    /// Empty the cache.
+    pub fn clear(&mut self) {
+        *self = Cache::new(self.config.clone());
+    }
+}
+
+impl FromStr for Replacement {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "lru" => Ok(Replacement::Lru),
+            "fifo" => Ok(Replacement::Fifo),
+            "random" => Ok(Replacement::Random),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromStr for WritePolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "back" | "write-back" => Ok(WritePolicy::WriteBack),
+            "through" | "write-through" => Ok(WritePolicy::WriteThrough),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache() {
+        // 2 sets of 2 blocks of 16 bytes
+        let config = CacheConfig {
+            size: 64,
+            assoc: 2,
+            block: 16,
+            ..CacheConfig::default()
+        };
+        let mut cache = Cache::new(config.clone());
+        assert_eq!(cache.read(0, 0), (miss(false), 10));
+        assert!(cache.read(8, 20).0.hit);
+        // 32 and 64 map to the same set as 0
+        assert!(!cache.read(32, 40).0.hit);
+        assert!(cache.read(0, 60).0.hit);
+        // evicts 32, the least recently used
+        assert!(!cache.write(64, 80).hit);
+        assert!(cache.read(0, 100).0.hit);
+        // evicts the dirty block of 64
+        assert_eq!(cache.read(32, 120), (miss(true), 20));
+
+        let mut cache = Cache::new(CacheConfig {
+            replacement: Replacement::Fifo,
+            write_policy: WritePolicy::WriteThrough,
+            ..config
+        });
+        cache.read(0, 0);
+        cache.read(32, 20);
+        cache.read(0, 40);
+        // evicts 0, loaded first
+        cache.read(64, 60);
+        assert!(!cache.read(0, 80).0.hit);
+        // stores neither allocate nor evict
+        assert_eq!(cache.write(128, 100), miss(true));
+        assert!(!cache.read(128, 120).0.hit);
+
+        // the block is only there once it arrived
+        let mut cache = Cache::new(CacheConfig::default());
+        assert_eq!(cache.read(0, 1).1, 10);
+        assert_eq!(cache.read(8, 4), (merged(), 7));
+        assert!(cache.write(16, 5).merged);
+        assert!(cache.read(8, 11).0.hit);
+
+        let mut cache = Cache::new(CacheConfig {
+            mshrs: Some(1),
+            ..CacheConfig::default()
+        });
+        assert_eq!(cache.read(0, 1).1, 10);
+        // merges into the miss of the same block
+        assert_eq!(cache.read(8, 4), (merged(), 7));
+        assert_eq!(cache.free_mshrs(4), Some(0));
+        assert_eq!(cache.primary_miss(64, 4), Some(2));
+        assert_eq!(cache.primary_miss(8, 4), None);
+        // waits for the register until the first block arrives
+        assert_eq!(cache.read(64, 4).1, 7 + 10);
+        assert_eq!(cache.free_mshrs(21), Some(1));
+        assert!(cache.read(0, 21).0.hit);
+    }
+
+    fn merged() -> CacheAccess {
+        CacheAccess {
+            hit: false,
+            mem_write: false,
+            merged: true,
+        }
+    }
+
+    fn miss(mem_write: bool) -> CacheAccess {
+        CacheAccess {
+            hit: false,
+            mem_write,
+            merged: false,
+        }
+    }
+}
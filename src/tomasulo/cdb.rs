@@ -0,0 +1,88 @@
+use std::str::FromStr;
+
+use super::*;
+
+/// How the common data buses are given to the stations ready to write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CdbPolicy {
+    /// The instructions issued first write first.
+    #[default]
+    Oldest,
+    /// Stations write in the order of their type: ADD, MULT, LOAD,
+    /// STORE, BRANCH then INT.
+    Priority,
+    /// Stations take turns, starting after the last station which wrote.
+    RoundRobin,
+}
+
+/// The common data buses.
+///
+/// Every result is broadcast on a bus, except the ones of stores
+/// which have nothing to broadcast. Stations which do not get a bus
+/// keep their result and try again in the next cycle.
+#[derive(Debug, Clone)]
+pub struct Cdb {
+    /// The number of buses, `None` if it is unlimited.
+    count: Option<usize>,
+    policy: CdbPolicy,
+    /// The last station which wrote, for round-robin.
+    last: Option<RsId>,
+}
+
+impl Cdb {
+    pub fn new(config: &MachineConfig) -> Cdb {
+        Cdb {
+            count: config.cdb_count(),
+            policy: config.cdb_policy(),
+            last: None,
+        }
+    }
+
+    /// Choose the stations which write their result in this cycle
+    /// among the `ready` ones. Returns the stations which write and
+    /// the ones which have to wait for a bus.
+    pub fn arbitrate(&mut self, ready: &[&RsInner]) -> (Vec<RsId>, Vec<RsId>) {
+        let (stores, mut ready): (Vec<&RsInner>, Vec<&RsInner>) = ready
+            .iter()
+            .copied()
+            .partition(|rs| rs.inst().is_some_and(|inst| inst.op == Type::SD));
+        let seq = |rs: &RsInner| rs.inst().map_or(usize::MAX, |inst| inst.seq);
+        match self.policy {
+            CdbPolicy::Oldest => ready.sort_by_key(|rs| seq(rs)),
+            CdbPolicy::Priority => ready.sort_by_key(|rs| rs.id),
+            CdbPolicy::RoundRobin => {
+                ready.sort_by_key(|rs| rs.id);
+                let first = ready
+                    .iter()
+                    .position(|rs| self.last.is_some_and(|last| rs.id > last))
+                    .unwrap_or(0);
+                ready.rotate_left(first);
+            }
+        }
+
+        let count = self.count.unwrap_or(ready.len()).min(ready.len());
+        let delayed = ready.split_off(count);
+        if let Some(rs) = ready.last() {
+            self.last = Some(rs.id);
+        }
+        let write = stores.iter().chain(ready.iter()).map(|rs| rs.id).collect();
+        (write, delayed.iter().map(|rs| rs.id).collect())
+    }
+
+    pub fn clear(&mut self) {
+        self.last = None;
+    }
+}
+
+impl FromStr for CdbPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "oldest" => Ok(CdbPolicy::Oldest),
+            "priority" => Ok(CdbPolicy::Priority),
+            "round-robin" => Ok(CdbPolicy::RoundRobin),
+            _ => Err(()),
+        }
+    }
+}
@@ -0,0 +1,1000 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::str::FromStr;
+
+use super::*;
+
+/// The description of the simulated machine.
+///
+/// Use the `with_*` methods to build a configuration from the
+/// default one, or load it from a textual configuration file:
+///
+/// ```text
+/// # reservation stations
+/// rs.add = 3
+/// rs.mult = 2
+/// rs.load = 3
+/// rs.store = 3
+/// rs.branch = 2
+/// rs.int = 2
+///
+/// # share one adder and one multiplier between the stations,
+/// # started in program order
+/// unit.adder = 1
+/// unit.multiplier = 1
+/// unit.policy = oldest
+///
+/// # a pipelined multiplier taking 6 cycles, accepting an
+/// # instruction every cycle
+/// unit.multiplier.latency = 6
+/// unit.multiplier.interval = 1
+///
+/// # latencies in cycles
+/// latency.MULTD = 10
+///
+/// # number of double precision and integer registers
+/// regs.fp = 16
+/// regs.int = 32
+///
+/// # enable the reorder buffer with 8 entries
+/// rob.size = 8
+///
+/// # compute numbers instead of symbolic expressions
+/// eval = numeric
+///
+/// # make loads wait for every earlier store, unless they can
+/// # take the value of the store to their address
+/// lsq.policy = conservative
+/// lsq.forwarding = true
+///
+/// # a 1KB 2-way data cache with 32 byte blocks, where misses
+/// # take 10 more cycles
+/// cache.size = 1024
+/// cache.assoc = 2
+/// cache.block = 32
+/// cache.replacement = lru
+/// cache.write = back
+/// cache.miss_penalty = 10
+///
+/// # track up to 4 outstanding misses, merging the ones to the
+/// # same block
+/// cache.mshrs = 4
+///
+/// # two common data buses, given to the oldest instructions first
+/// cdb.count = 2
+/// cdb.policy = oldest
+///
+/// # a 4KB memory of 8 byte aligned doubles, where other
+/// # addresses raise an exception
+/// mem.size = 4096
+/// mem.align = 8
+///
+/// # raise an external interrupt at cycle 30
+/// interrupt.cycle = 30
+///
+/// # issue up to 2 instructions per cycle
+/// issue.width = 2
+///
+/// # stop simulating after 1000 cycles
+/// max_cycles = 1000
+///
+/// # predict branches with a table of 16 2-bit counters
+/// branch.predictor = 2bit
+/// branch.entries = 16
+/// branch.history = 4
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MachineConfig {
+    rs_count: BTreeMap<RsType, usize>,
+    eu_count: BTreeMap<EuType, usize>,
+    eu_interval: BTreeMap<EuType, u64>,
+    dispatch_policy: DispatchPolicy,
+    latency: HashMap<Type, u64>,
+    fp_regs: usize,
+    int_regs: usize,
+    rob_size: Option<usize>,
+    eval_mode: EvalMode,
+    issue_width: usize,
+    cdb_count: Option<usize>,
+    cdb_policy: CdbPolicy,
+    lsq_policy: LsqPolicy,
+    forwarding: bool,
+    cache: Option<CacheConfig>,
+    mem_size: Option<i64>,
+    mem_align: i64,
+    interrupt: Option<u64>,
+    max_cycles: u64,
+    predictor: PredictorKind,
+    predictor_entries: usize,
+    history_bits: u32,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The configuration file can not be read.
+    Io(std::io::Error),
+    /// A line of the configuration file is invalid.
+    Invalid { line: usize, message: String },
+    /// A setting of a configuration built with the `with_*` methods
+    /// is out of range.
+    Value(String),
+}
+
+impl Default for MachineConfig {
+    fn default() -> MachineConfig {
+        MachineConfig {
+            rs_count: BTreeMap::from([
+                (RsType::ADD, 3),
+                (RsType::MULT, 2),
+                (RsType::LOAD, 3),
+                (RsType::STORE, 3),
+                (RsType::BRANCH, 2),
+                (RsType::INT, 2),
+            ]),
+            eu_count: BTreeMap::new(),
+            eu_interval: BTreeMap::new(),
+            dispatch_policy: DispatchPolicy::Oldest,
+            latency: HashMap::from([
+                (Type::ADDD, 2),
+                (Type::SUBD, 2),
+                (Type::MULTD, 10),
+                (Type::DIVD, 20),
+                (Type::LD, 2),
+                (Type::SD, 2),
+                (Type::BEQ, 1),
+                (Type::BNE, 1),
+                (Type::BEQZ, 1),
+                (Type::BNEZ, 1),
+                (Type::ADD, 1),
+                (Type::SUB, 1),
+                (Type::ADDI, 1),
+                (Type::SUBI, 1),
+                (Type::DADDI, 1),
+                (Type::DADDUI, 1),
+            ]),
+            fp_regs: 16,
+            int_regs: 32,
+            rob_size: None,
+            eval_mode: EvalMode::Symbolic,
+            issue_width: 1,
+            cdb_count: None,
+            cdb_policy: CdbPolicy::Oldest,
+            lsq_policy: LsqPolicy::Address,
+            forwarding: false,
+            cache: None,
+            mem_size: None,
+            mem_align: 1,
+            interrupt: None,
+            max_cycles: 1000,
+            predictor: PredictorKind::NotTaken,
+            predictor_entries: 16,
+            history_bits: 4,
+        }
+    }
+}
+
+impl MachineConfig {
+    /// Load the configuration from a file.
+    pub fn load(path: impl AsRef<Path>) -> Result<MachineConfig, ConfigError> {
+        std::fs::read_to_string(path)
+            .map_err(ConfigError::Io)?
+            .parse()
+    }
+
+    /// Set the number of reservation stations of a type.
+    pub fn with_rs_count(mut self, rs_type: RsType, count: usize) -> Self {
+        self.rs_count.insert(rs_type, count);
+        self
+    }
+
+    /// Share `count` execution units of a type between the stations.
+    pub fn with_eu_count(mut self, eu_type: EuType, count: usize) -> Self {
+        self.eu_count.insert(eu_type, count);
+        self
+    }
+
+    /// Set the latency of every instruction type executed by a type of unit.
+    pub fn with_eu_latency(mut self, eu_type: EuType, cycles: u64) -> Self {
+        for (_, latency) in self
+            .latency
+            .iter_mut()
+            .filter(|(op, _)| EuType::from(**op) == eu_type)
+        {
+            *latency = cycles;
+        }
+        self
+    }
+
+    /// Pipeline the units of a type, which then accept an instruction
+    /// every `cycles` cycles instead of waiting for the previous one.
+    ///
+    /// The type needs a number of units set with
+    /// [`MachineConfig::with_eu_count`].
+    pub fn with_eu_interval(mut self, eu_type: EuType, cycles: u64) -> Self {
+        self.eu_interval.insert(eu_type, cycles);
+        self
+    }
+
+    /// Set how the execution units are given to the stations.
+    pub fn with_dispatch_policy(mut self, policy: DispatchPolicy) -> Self {
+        self.dispatch_policy = policy;
+        self
+    }
+
+    /// Set the execution latency of an instruction type.
+    pub fn with_latency(mut self, op: Type, cycles: u64) -> Self {
+        self.latency.insert(op, cycles);
+        self
+    }
+
+    /// Set the number of floating point registers.
+    pub fn with_fp_regs(mut self, count: usize) -> Self {
+        self.fp_regs = count;
+        self
+    }
+
+    /// Set the number of integer registers.
+    pub fn with_int_regs(mut self, count: usize) -> Self {
+        self.int_regs = count;
+        self
+    }
+
+    /// Enable the reorder buffer with `size` entries. A size of 0
+    /// disables it, as in the loader.
+    pub fn with_rob_size(mut self, size: usize) -> Self {
+        self.rob_size = match size {
+            0 => None,
+            size => Some(size),
+        };
+        self
+    }
+
+    /// Set how results are computed.
+    pub fn with_eval_mode(mut self, mode: EvalMode) -> Self {
+        self.eval_mode = mode;
+        self
+    }
+
+    /// Set the number of instructions issued per cycle.
+    pub fn with_issue_width(mut self, width: usize) -> Self {
+        self.issue_width = width;
+        self
+    }
+
+    /// Limit the number of common data buses, which are unlimited by
+    /// default. A count of 0 keeps them unlimited, as in the loader.
+    pub fn with_cdb_count(mut self, count: usize) -> Self {
+        self.cdb_count = match count {
+            0 => None,
+            count => Some(count),
+        };
+        self
+    }
+
+    /// Set how the common data buses are arbitrated.
+    pub fn with_cdb_policy(mut self, policy: CdbPolicy) -> Self {
+        self.cdb_policy = policy;
+        self
+    }
+
+    /// Set how loads and stores are ordered.
+    pub fn with_lsq_policy(mut self, policy: LsqPolicy) -> Self {
+        self.lsq_policy = policy;
+        self
+    }
+
+    /// Let loads take the value of an earlier store to their address.
+    pub fn with_forwarding(mut self, forwarding: bool) -> Self {
+        self.forwarding = forwarding;
+        self
+    }
+
+    /// Put a data cache in front of the memory. As in the loader, a
+    /// size of 0 removes the cache and 0 MSHRs make it blocking.
+    pub fn with_cache(mut self, mut cache: CacheConfig) -> Self {
+        if cache.mshrs == Some(0) {
+            cache.mshrs = None;
+        }
+        self.cache = (cache.size != 0).then_some(cache);
+        self
+    }
+
+    /// Raise an exception on loads and stores outside `[0, size)`.
+    /// A size of 0 makes every address valid, as in the loader.
+    pub fn with_mem_size(mut self, size: i64) -> Self {
+        self.mem_size = match size {
+            0 => None,
+            size => Some(size),
+        };
+        self
+    }
+
+    /// Raise an exception on loads and stores to an address
+    /// which is not a multiple of `align`.
+    pub fn with_mem_align(mut self, align: i64) -> Self {
+        self.mem_align = align;
+        self
+    }
+
+    /// Raise an external interrupt at a cycle.
+    pub fn with_interrupt(mut self, cycle: u64) -> Self {
+        self.interrupt = Some(cycle);
+        self
+    }
+
+    /// Set the number of cycles after which the simulation stops.
+    pub fn with_max_cycles(mut self, cycles: u64) -> Self {
+        self.max_cycles = cycles;
+        self
+    }
+
+    /// Set the branch predictor.
+    pub fn with_predictor(mut self, kind: PredictorKind) -> Self {
+        self.predictor = kind;
+        self
+    }
+
+    /// Set the table size and global history length of the branch predictor.
+    pub fn with_predictor_size(mut self, entries: usize, history_bits: u32) -> Self {
+        self.predictor_entries = entries;
+        self.history_bits = history_bits;
+        self
+    }
+
+    #[inline]
+    pub fn rs_count(&self, rs_type: RsType) -> usize {
+        self.rs_count.get(&rs_type).copied().unwrap_or(0)
+    }
+
+    /// The number of execution units of a type, `None` if every
+    /// station executes on its own unit.
+    #[inline]
+    pub fn eu_count(&self, eu_type: EuType) -> Option<usize> {
+        self.eu_count.get(&eu_type).copied()
+    }
+
+    /// The initiation interval of the units of a type, `None` if they
+    /// are not pipelined.
+    #[inline]
+    pub fn eu_interval(&self, eu_type: EuType) -> Option<u64> {
+        self.eu_interval.get(&eu_type).copied()
+    }
+
+    #[inline]
+    pub fn dispatch_policy(&self) -> DispatchPolicy {
+        self.dispatch_policy
+    }
+
+    /// The execution latency of an instruction type. Every type has
+    /// one in the default configuration, and [`MachineConfig::validate`]
+    /// rejects a configuration missing one.
+    #[inline]
+    pub fn latency(&self, op: Type) -> u64 {
+        self.latency[&op]
+    }
+
+    #[inline]
+    pub fn fp_regs(&self) -> usize {
+        self.fp_regs
+    }
+
+    #[inline]
+    pub fn int_regs(&self) -> usize {
+        self.int_regs
+    }
+
+    /// The size of the reorder buffer, `None` if it is disabled.
+    #[inline]
+    pub fn rob_size(&self) -> Option<usize> {
+        self.rob_size
+    }
+
+    #[inline]
+    pub fn eval_mode(&self) -> EvalMode {
+        self.eval_mode
+    }
+
+    #[inline]
+    pub fn issue_width(&self) -> usize {
+        self.issue_width
+    }
+
+    /// The number of common data buses, `None` if it is unlimited.
+    #[inline]
+    pub fn cdb_count(&self) -> Option<usize> {
+        self.cdb_count
+    }
+
+    #[inline]
+    pub fn cdb_policy(&self) -> CdbPolicy {
+        self.cdb_policy
+    }
+
+    #[inline]
+    pub fn lsq_policy(&self) -> LsqPolicy {
+        self.lsq_policy
+    }
+
+    #[inline]
+    pub fn forwarding(&self) -> bool {
+        self.forwarding
+    }
+
+    /// The data cache, `None` if every access takes the same time.
+    #[inline]
+    pub fn cache(&self) -> Option<&CacheConfig> {
+        self.cache.as_ref()
+    }
+
+    /// The size of the memory in bytes, `None` if every address is valid.
+    #[inline]
+    pub fn mem_size(&self) -> Option<i64> {
+        self.mem_size
+    }
+
+    /// The alignment of memory accesses in bytes, 1 if any address is valid.
+    #[inline]
+    pub fn mem_align(&self) -> i64 {
+        self.mem_align
+    }
+
+    /// The cycle an external interrupt is raised at.
+    #[inline]
+    pub fn interrupt(&self) -> Option<u64> {
+        self.interrupt
+    }
+
+    #[inline]
+    pub fn max_cycles(&self) -> u64 {
+        self.max_cycles
+    }
+
+    #[inline]
+    pub fn predictor(&self) -> PredictorKind {
+        self.predictor
+    }
+
+    /// Build the configured branch predictor.
+    pub fn build_predictor(&self) -> Box<dyn Predictor> {
+        self.predictor
+            .build(self.predictor_entries, self.history_bits)
+    }
+
+    /// Check that every setting is in range.
+    ///
+    /// The loader checks the configuration after every line, and
+    /// `Executer::new` checks the ones built with the `with_*` methods.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.check()
+            .and_then(|_| self.check_combined())
+            .map_err(ConfigError::Value)
+    }
+
+    /// Check the settings which only make sense together. The loader
+    /// checks them once the whole file is read, so that the order of
+    /// the lines does not matter.
+    fn check_combined(&self) -> Result<(), String> {
+        // without a unit count every station has its own unit, which
+        // never holds more than one instruction
+        if let Some(eu_type) = self
+            .eu_interval
+            .keys()
+            .find(|eu_type| !self.eu_count.contains_key(eu_type))
+        {
+            return Err(format!(
+                "interval of the {eu_type:?} units needs a number of units"
+            ));
+        }
+        // without a reorder buffer issue stalls until a branch is resolved
+        if self.rob_size.is_none()
+            && !matches!(
+                self.predictor,
+                PredictorKind::Taken | PredictorKind::NotTaken
+            )
+        {
+            return Err(format!(
+                "the {:?} predictor needs a reorder buffer",
+                self.predictor
+            ));
+        }
+        if let Some(cache) = self.cache.as_ref() {
+            let set = cache
+                .block
+                .checked_mul(cache.assoc)
+                .ok_or("cache sets are too large")?;
+            if cache.size % set != 0 {
+                return Err(format!(
+                    "cache size must be a multiple of {set}, the block size times the associativity"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn check(&self) -> Result<(), String> {
+        // stations and units are numbered with a u8
+        let limit = u8::MAX as usize + 1;
+        if let Some((rs_type, _)) = self.rs_count.iter().find(|(_, n)| **n > limit) {
+            return Err(format!("at most {limit} {rs_type:?} reservation stations"));
+        }
+        if let Some((eu_type, _)) = self.eu_count.iter().find(|(_, n)| **n > limit) {
+            return Err(format!("at most {limit} {eu_type:?} units"));
+        }
+        if let Some(op) = Type::ALL.iter().find(|op| !self.latency.contains_key(op)) {
+            return Err(format!("no latency for {op:?}"));
+        }
+        let mut latency = self.latency.iter().collect::<Vec<_>>();
+        latency.sort_by_key(|(op, _)| format!("{op:?}"));
+        if let Some((op, _)) = latency.iter().find(|(_, cycles)| **cycles == 0) {
+            return Err(format!("latency of {op:?} must be at least 1 cycle"));
+        }
+        if let Some((eu_type, _)) = self.eu_interval.iter().find(|(_, cycles)| **cycles == 0) {
+            return Err(format!(
+                "interval of the {eu_type:?} units must be at least 1 cycle"
+            ));
+        }
+        if self.fp_regs > 128 {
+            return Err("at most 128 floating point registers".to_string());
+        }
+        if self.int_regs > u8::MAX as usize {
+            return Err(format!("at most {} integer registers", u8::MAX));
+        }
+        if self.rob_size.is_some_and(|size| size > u8::MAX as usize) {
+            return Err(format!("reorder buffer size must be at most {}", u8::MAX));
+        }
+        if self.issue_width == 0 {
+            return Err("issue width must be at least 1".to_string());
+        }
+        if self.max_cycles == 0 {
+            return Err("cycle limit must be at least 1".to_string());
+        }
+        if self.predictor_entries == 0 {
+            return Err("predictor needs at least 1 entry".to_string());
+        }
+        if self.history_bits > 16 {
+            return Err("history must be at most 16 bits".to_string());
+        }
+        if let Some(cache) = self.cache.as_ref() {
+            for (name, count) in [
+                ("size", cache.size),
+                ("assoc", cache.assoc),
+                ("block", cache.block),
+            ] {
+                if count == 0 {
+                    return Err(format!("cache {name} must be at least 1"));
+                }
+            }
+        }
+        if self.mem_align < 1 {
+            return Err("alignment must be at least 1".to_string());
+        }
+        if self.mem_size.is_some_and(|size| size < 0) {
+            return Err("memory size must not be negative".to_string());
+        }
+        Ok(())
+    }
+
+    /// Apply a single `key = value` setting.
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let (section, name) = key.split_once('.').unwrap_or((key, ""));
+        match section {
+            "rs" => {
+                let rs_type = name
+                    .parse::<RsType>()
+                    .map_err(|_| format!("unknown reservation station type `{name}`"))?;
+                self.rs_count.insert(rs_type, parse_num(value)?);
+            }
+            "unit" if name == "policy" => {
+                self.dispatch_policy = value
+                    .parse()
+                    .map_err(|_| format!("unknown dispatch policy `{value}`"))?;
+            }
+            "unit" => {
+                let (unit, field) = name.split_once('.').unwrap_or((name, ""));
+                let eu_type = unit
+                    .parse::<EuType>()
+                    .map_err(|_| format!("unknown execution unit `{unit}`"))?;
+                if field.is_empty() {
+                    match parse_num(value)? {
+                        0 => self.eu_count.remove(&eu_type),
+                        count => self.eu_count.insert(eu_type, count),
+                    };
+                    return Ok(());
+                }
+                let cycles = parse_num(value)?;
+                match field {
+                    "latency" => *self = std::mem::take(self).with_eu_latency(eu_type, cycles),
+                    "interval" => {
+                        self.eu_interval.insert(eu_type, cycles);
+                    }
+                    _ => return Err(format!("unknown key `{key}`")),
+                }
+            }
+            "latency" => {
+                let op = name
+                    .to_uppercase()
+                    .parse::<Type>()
+                    .map_err(|_| format!("unknown instruction `{name}`"))?;
+                self.latency.insert(op, parse_num(value)?);
+            }
+            "regs" if name == "fp" => {
+                self.fp_regs = parse_num(value)?;
+            }
+            "regs" if name == "int" => {
+                self.int_regs = parse_num(value)?;
+            }
+            "rob" if name == "size" => {
+                self.rob_size = match parse_num(value)? {
+                    0 => None,
+                    size => Some(size),
+                };
+            }
+            "eval" if name.is_empty() => {
+                self.eval_mode = value
+                    .parse()
+                    .map_err(|_| format!("unknown evaluation mode `{value}`"))?;
+            }
+            "issue" if name == "width" => {
+                self.issue_width = parse_num(value)?;
+            }
+            "cdb" if name == "count" => {
+                self.cdb_count = match parse_num(value)? {
+                    0 => None,
+                    count => Some(count),
+                };
+            }
+            "cdb" if name == "policy" => {
+                self.cdb_policy = value
+                    .parse()
+                    .map_err(|_| format!("unknown CDB policy `{value}`"))?;
+            }
+            "lsq" if name == "policy" => {
+                self.lsq_policy = value
+                    .parse()
+                    .map_err(|_| format!("unknown load/store queue policy `{value}`"))?;
+            }
+            "lsq" if name == "forwarding" => {
+                self.forwarding = value
+                    .parse()
+                    .map_err(|_| format!("expected `true` or `false`, found `{value}`"))?;
+            }
+            "cache" if name == "size" && parse_num::<usize>(value)? == 0 => {
+                self.cache = None;
+            }
+            "cache" => {
+                let cache = self.cache.get_or_insert_with(CacheConfig::default);
+                match name {
+                    "size" | "assoc" | "block" => {
+                        let count = parse_num(value)?;
+                        match name {
+                            "size" => cache.size = count,
+                            "assoc" => cache.assoc = count,
+                            _ => cache.block = count,
+                        }
+                    }
+                    "replacement" => {
+                        cache.replacement = value
+                            .parse()
+                            .map_err(|_| format!("unknown replacement policy `{value}`"))?;
+                    }
+                    "write" => {
+                        cache.write_policy = value
+                            .parse()
+                            .map_err(|_| format!("unknown write policy `{value}`"))?;
+                    }
+                    "miss_penalty" => cache.miss_penalty = parse_num(value)?,
+                    "mshrs" => {
+                        cache.mshrs = match parse_num(value)? {
+                            0 => None,
+                            count => Some(count),
+                        };
+                    }
+                    _ => return Err(format!("unknown key `{key}`")),
+                }
+            }
+            "mem" => match name {
+                "size" => {
+                    self.mem_size = match parse_num(value)? {
+                        0 => None,
+                        size => Some(size),
+                    };
+                }
+                "align" => self.mem_align = parse_num(value)?,
+                _ => return Err(format!("unknown key `{key}`")),
+            },
+            "interrupt" if name == "cycle" => {
+                self.interrupt = Some(parse_num(value)?);
+            }
+            "max_cycles" if name.is_empty() => {
+                self.max_cycles = parse_num(value)?;
+            }
+            "branch" => match name {
+                "predictor" => {
+                    self.predictor = value
+                        .parse()
+                        .map_err(|_| format!("unknown predictor `{value}`"))?;
+                }
+                "entries" => self.predictor_entries = parse_num(value)?,
+                "history" => self.history_bits = parse_num(value)?,
+                _ => return Err(format!("unknown key `{key}`")),
+            },
+            _ => return Err(format!("unknown key `{key}`")),
+        }
+        Ok(())
+    }
+}
+
+fn parse_num<T: FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid number `{value}`"))
+}
+
+impl FromStr for MachineConfig {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut config = MachineConfig::default();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = |message| ConfigError::Invalid {
+                line: i + 1,
+                message,
+            };
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid("expected `key = value`".to_string()))?;
+            config.set(key.trim(), value.trim()).map_err(invalid)?;
+            config.check().map_err(invalid)?;
+        }
+        config.check_combined().map_err(ConfigError::Value)?;
+        Ok(config)
+    }
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "can not read config: {e}"),
+            ConfigError::Invalid { line, message } => write!(f, "line {line}: {message}"),
+            ConfigError::Value(message) => write!(f, "invalid config: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tomasulo::cache::{Replacement, WritePolicy};
+
+    #[test]
+    fn test_parse_config() {
+        let config = r"
+        # a smaller machine
+        rs.add = 1
+        rs.MULT = 4
+        latency.multd = 6   # faster multiplier
+        unit.Multiplier = 1
+        unit.policy = priority
+        unit.adder.latency = 3
+        unit.divider = 2
+        unit.divider.interval = 4
+        regs.fp = 8
+        rob.size = 6
+        branch.predictor = gshare
+        eval = numeric
+        issue.width = 4
+        cdb.count = 1
+        cdb.policy = round-robin
+        lsq.policy = conservative
+        lsq.forwarding = true
+        cache.assoc = 4
+        cache.replacement = fifo
+        cache.write = through
+        cache.mshrs = 2
+        mem.size = 4096
+        mem.align = 8
+        interrupt.cycle = 30
+        max_cycles = 200"
+            .parse::<MachineConfig>()
+            .unwrap();
+
+        assert_eq!(config.rs_count(RsType::ADD), 1);
+        assert_eq!(config.rs_count(RsType::MULT), 4);
+        assert_eq!(config.rs_count(RsType::LOAD), 3);
+        assert_eq!(config.latency(Type::MULTD), 6);
+        assert_eq!(config.eu_count(EuType::Multiplier), Some(1));
+        assert_eq!(config.eu_count(EuType::Adder), None);
+        assert_eq!(config.dispatch_policy(), DispatchPolicy::Priority);
+        assert_eq!(config.latency(Type::ADDD), 3);
+        assert_eq!(config.latency(Type::SUBD), 3);
+        assert_eq!(config.eu_interval(EuType::Divider), Some(4));
+        assert_eq!(config.eu_interval(EuType::Multiplier), None);
+        assert_eq!(config.latency(Type::DIVD), 20);
+        assert_eq!(config.fp_regs(), 8);
+        assert_eq!(config.rob_size(), Some(6));
+        assert_eq!(config.predictor(), PredictorKind::Gshare);
+        assert_eq!(config.eval_mode(), EvalMode::Numeric);
+        assert_eq!(config.issue_width(), 4);
+        assert_eq!(config.cdb_count(), Some(1));
+        assert_eq!(config.cdb_policy(), CdbPolicy::RoundRobin);
+        assert_eq!(config.lsq_policy(), LsqPolicy::Conservative);
+        assert!(config.forwarding());
+        let cache = config.cache().unwrap();
+        assert_eq!((cache.size, cache.assoc), (1024, 4));
+        assert_eq!(cache.replacement, Replacement::Fifo);
+        assert_eq!(cache.write_policy, WritePolicy::WriteThrough);
+        assert_eq!(cache.mshrs, Some(2));
+        assert_eq!(config.mem_size(), Some(4096));
+        assert_eq!(config.mem_align(), 8);
+        assert_eq!(config.interrupt(), Some(30));
+        assert_eq!(config.max_cycles(), 200);
+
+        assert!("rs.fpu = 1".parse::<MachineConfig>().is_err());
+        assert!("latency.ADDD = 0".parse::<MachineConfig>().is_err());
+        assert!("rs.add".parse::<MachineConfig>().is_err());
+        assert!("unit.fpu = 1".parse::<MachineConfig>().is_err());
+        assert!("unit.adder.interval = 0".parse::<MachineConfig>().is_err());
+        assert!("unit.adder.interval = 1".parse::<MachineConfig>().is_err());
+        assert!("unit.adder.interval = 1\nunit.adder = 1"
+            .parse::<MachineConfig>()
+            .is_ok());
+        assert!("cache.block = 0".parse::<MachineConfig>().is_err());
+        assert!("cache.size = 16".parse::<MachineConfig>().is_err());
+        // the geometry is checked once every line is read
+        assert!("cache.size = 48\ncache.assoc = 3\ncache.block = 16"
+            .parse::<MachineConfig>()
+            .is_ok());
+        assert!("mem.align = 0".parse::<MachineConfig>().is_err());
+        assert!("issue.width = 0".parse::<MachineConfig>().is_err());
+        assert!("rob.size = 256".parse::<MachineConfig>().is_err());
+        assert!("rs.add = 300".parse::<MachineConfig>().is_err());
+        assert!("unit.adder = 257".parse::<MachineConfig>().is_err());
+        assert_eq!(
+            "rob.size = 0".parse::<MachineConfig>().unwrap().rob_size(),
+            None
+        );
+        assert!("regs.fp = 129".parse::<MachineConfig>().is_err());
+        assert!("cache.size = 0"
+            .parse::<MachineConfig>()
+            .unwrap()
+            .cache()
+            .is_none());
+    }
+
+    #[test]
+    fn test_validate() {
+        let config = MachineConfig::default();
+        assert!(config.validate().is_ok());
+        for op in Type::ALL {
+            assert!(config.latency(op) >= 1);
+        }
+        let mut missing = config.clone();
+        missing.latency.remove(&Type::DADDUI);
+        assert_eq!(
+            missing.validate().unwrap_err().to_string(),
+            "invalid config: no latency for DADDUI"
+        );
+        assert!(config
+            .clone()
+            .with_latency(Type::ADDD, 0)
+            .validate()
+            .is_err());
+        assert!(config
+            .clone()
+            .with_eu_latency(EuType::Adder, 0)
+            .validate()
+            .is_err());
+        assert!(config
+            .clone()
+            .with_eu_interval(EuType::Adder, 0)
+            .validate()
+            .is_err());
+        // the predictor is only used with a reorder buffer
+        let gshare = config.clone().with_predictor(PredictorKind::Gshare);
+        assert!(gshare.validate().is_err());
+        assert!(gshare.with_rob_size(8).validate().is_ok());
+        assert!(config
+            .clone()
+            .with_predictor(PredictorKind::Taken)
+            .validate()
+            .is_ok());
+        assert!("branch.predictor = 2bit".parse::<MachineConfig>().is_err());
+        // an interval without units would be ignored
+        assert!(config
+            .clone()
+            .with_eu_interval(EuType::Adder, 1)
+            .validate()
+            .is_err());
+        assert!(config
+            .clone()
+            .with_eu_interval(EuType::Adder, 1)
+            .with_eu_count(EuType::Adder, 1)
+            .validate()
+            .is_ok());
+        assert!(config.clone().with_predictor_size(0, 4).validate().is_err());
+        assert!(config
+            .clone()
+            .with_predictor_size(16, 17)
+            .validate()
+            .is_err());
+        // 0 disables the features which are off by default
+        assert_eq!(config.clone().with_rob_size(0).rob_size(), None);
+        assert_eq!(config.clone().with_mem_size(0).mem_size(), None);
+        let none = CacheConfig {
+            size: 0,
+            ..CacheConfig::default()
+        };
+        assert!(config.clone().with_cache(none).cache().is_none());
+        assert!(config
+            .clone()
+            .with_rs_count(RsType::ADD, 256)
+            .validate()
+            .is_ok());
+        assert!(config
+            .clone()
+            .with_rs_count(RsType::ADD, 257)
+            .validate()
+            .is_err());
+        assert!(config
+            .clone()
+            .with_eu_count(EuType::Adder, 300)
+            .validate()
+            .is_err());
+        assert!(config.clone().with_rob_size(256).validate().is_err());
+        assert!(config.clone().with_issue_width(0).validate().is_err());
+        assert!(config.clone().with_max_cycles(0).validate().is_err());
+        assert_eq!(config.clone().with_cdb_count(0).cdb_count(), None);
+        assert!(config.clone().with_fp_regs(129).validate().is_err());
+        assert!(config.clone().with_mem_align(0).validate().is_err());
+        let cache = CacheConfig::default();
+        let block = CacheConfig {
+            block: 0,
+            ..cache.clone()
+        };
+        let assoc = CacheConfig {
+            assoc: 0,
+            ..cache.clone()
+        };
+        let mshrs = CacheConfig {
+            mshrs: Some(0),
+            ..cache
+        };
+        assert!(config.clone().with_cache(block).validate().is_err());
+        assert!(config.clone().with_cache(assoc).validate().is_err());
+        // 16 bytes can not hold 2 blocks of 32 bytes
+        let small = CacheConfig {
+            size: 16,
+            ..CacheConfig::default()
+        };
+        assert!(config.clone().with_cache(small).validate().is_err());
+        let uneven = CacheConfig {
+            size: 96,
+            ..CacheConfig::default()
+        };
+        assert!(config.clone().with_cache(uneven).validate().is_err());
+        let huge = CacheConfig {
+            assoc: usize::MAX,
+            ..CacheConfig::default()
+        };
+        assert!(config.clone().with_cache(huge).validate().is_err());
+        assert_eq!(
+            config.clone().with_cache(mshrs).cache().unwrap().mshrs,
+            None
+        );
+        assert!(config.clone().with_mem_size(-8).validate().is_err());
+        assert_eq!(
+            config
+                .with_latency(Type::MULTD, 0)
+                .validate()
+                .unwrap_err()
+                .to_string(),
+            "invalid config: latency of MULTD must be at least 1 cycle"
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use super::*;
+
+/// Why an instruction can not complete.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exception {
+    /// A DIVD by zero.
+    DivideByZero,
+    /// A load or store to an address which is not a multiple of
+    /// the configured alignment.
+    MisalignedAddress(i64),
+    /// A load or store outside of the configured memory size.
+    AddressOutOfRange(i64),
+    /// An external interrupt injected at a configured cycle.
+    Interrupt,
+}
+
+/// The state of the machine when an exception is taken.
+///
+/// With a reorder buffer exceptions are precise: they are taken when
+/// the faulting instruction reaches the head of the buffer, so every
+/// earlier instruction has committed and no later one has changed the
+/// registers or the memory. Without one they are taken as soon as they
+/// are detected, when later instructions may have written their result
+/// and earlier ones may still be executing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fault {
+    pub exception: Exception,
+    pub cycle: u64,
+    /// The faulting instruction, or the first one which had not
+    /// completed for an interrupt. `None` if the whole program had
+    /// completed.
+    pub inst: Option<Instruction>,
+    /// Later instructions which had already written their result.
+    pub completed: Vec<Instruction>,
+    /// Earlier instructions which had not completed and were squashed.
+    pub lost: Vec<Instruction>,
+    /// The registers, `None` for the ones whose value was lost.
+    pub regs: Vec<(Unit, Option<Value>)>,
+    pub mem: Memory,
+}
+
+impl Fault {
+    /// Whether the state is the one of a sequential execution stopped
+    /// right before the faulting instruction.
+    #[inline]
+    pub fn is_precise(&self) -> bool {
+        self.completed.is_empty() && self.lost.is_empty()
+    }
+}
+
+impl std::fmt::Display for Exception {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Exception::DivideByZero => write!(f, "divide by zero"),
+            Exception::MisalignedAddress(addr) => write!(f, "misaligned address {addr}"),
+            Exception::AddressOutOfRange(addr) => write!(f, "address {addr} out of range"),
+            Exception::Interrupt => write!(f, "external interrupt"),
+        }
+    }
+}
+
+impl std::fmt::Display for Fault {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} at cycle {}", self.exception, self.cycle)?;
+        if let Some(inst) = self.inst.as_ref() {
+            write!(f, " in `{}` (pc {})", inst.text(), inst.pc)?;
+        }
+        let list = |insts: &[Instruction]| {
+            let texts = insts.iter().map(|inst| format!("`{}`", inst.text()));
+            texts.collect::<Vec<_>>().join(", ")
+        };
+        match self.is_precise() {
+            true => write!(
+                f,
+                "\n  precise: the state is the one before the instruction"
+            )?,
+            false => write!(f, "\n  imprecise:")?,
+        }
+        if !self.completed.is_empty() {
+            write!(
+                f,
+                "\n    later instructions completed: {}",
+                list(&self.completed)
+            )?;
+        }
+        if !self.lost.is_empty() {
+            write!(f, "\n    earlier instructions lost: {}", list(&self.lost))?;
+        }
+
+        write!(f, "\n  registers:")?;
+        for (unit, value) in self.regs.iter() {
+            // skip the integer registers which were never written
+            if let (Unit::Regs(_), Some(value)) = (unit, value) {
+                if matches!(**value, ValueInner::Imm(0)) || **value == ValueInner::Unit(*unit) {
+                    continue;
+                }
+            }
+            match value {
+                Some(value) => write!(f, "\n    {unit} = {}", value.brief())?,
+                None => write!(f, "\n    {unit} = ?")?,
+            }
+        }
+        if self.mem.iter().next().is_some() {
+            write!(f, "\n  memory:")?;
+            for (addr, value) in self.mem.iter() {
+                write!(f, "\n    M[{addr}] = {value}")?;
+            }
+        }
+        Ok(())
+    }
+}
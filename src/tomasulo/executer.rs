@@ -1,140 +1,916 @@
-use console::style;
-use std::collections::VecDeque;
+use std::collections::HashMap;
 
+use super::render::ConsoleRenderer;
 use super::*;
 
+/// An error which stops the simulation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimError {
+    /// No instruction can make progress any more.
+    Deadlock {
+        cycle: u64,
+        /// The busy reservation stations and the tags they wait on.
+        waiting: Vec<(RsId, Vec<Tag>)>,
+        /// The type of reservation station the next instruction
+        /// waits for, if issue is stalled on one.
+        stalled: Option<RsType>,
+    },
+    /// The configured number of cycles has been simulated.
+    CycleLimit { cycles: u64 },
+    /// An instruction raised an exception, or an interrupt was raised.
+    Exception(Box<Fault>),
+    /// The program uses a register the machine does not have, in the
+    /// instruction at `pc` or in an initial value if `pc` is `None`.
+    BadRegister { pc: Option<usize>, reg: Unit },
+    /// The branch at `pc` jumps to a label which is not defined.
+    UndefinedLabel { pc: usize, label: String },
+    /// The condition of the branch at `pc` can not be evaluated, such
+    /// as one on a value loaded from memory in symbolic mode.
+    UndecidedBranch { pc: usize, condition: String },
+    /// A cycle could not be printed.
+    Output(std::io::ErrorKind),
+}
+
 pub struct Executer {
+    pub config: MachineConfig,
     pub rs: ReservationStation,
     pub fu: FloatingUnit,
-    pub insts: VecDeque<Instruction>,
+    pub int: IntegerUnit,
+    pub rob: Option<ReorderBuffer>,
+    pub eu: ExecUnits,
+    pub lsq: LoadStoreQueue,
+    pub cache: Option<Cache>,
+    pub cdb: Cdb,
+    pub mem: Memory,
+    pub predictor: Box<dyn Predictor>,
+    pub program: Vec<Instruction>,
+    pub labels: HashMap<String, usize>,
     pub insts_comp: Vec<Instruction>,
+    /// The instructions squashed after a mispredicted branch
+    /// or an exception.
+    pub squashed: Vec<Instruction>,
+    /// The events of the current cycle.
+    pub events: Vec<Event>,
+    /// The number of issued instructions which have not been squashed.
     pub inst_count: usize,
+    /// The sequence number of the next issued instruction.
+    pub seq: usize,
+    /// The index of the next instruction to issue.
+    pub pc: usize,
     pub cycle: u64,
     pub finished: bool,
+    /// Without a reorder buffer, issue stalls until a branch is resolved.
+    pub branch_pending: bool,
+    pub branches: u64,
+    pub mispredicts: u64,
+    pub stats: Stats,
+    /// Whether issue has run out of instructions, so that it is only
+    /// counted as a stall once while the pipeline drains.
+    drained: bool,
 }
 
 impl Executer {
-    pub fn new() -> Executer {
-        Executer {
-            rs: ReservationStation::new(),
-            fu: FloatingUnit::new(),
-            insts: VecDeque::new(),
+    /// Create an executer for a machine, failing if a setting of
+    /// the configuration is out of range.
+    pub fn new(config: MachineConfig) -> Result<Executer, ConfigError> {
+        config.validate()?;
+        let rs = ReservationStation::new(&config);
+        Ok(Executer {
+            stats: Stats::new(&config, rs.iter()),
+            rs,
+            fu: FloatingUnit::new(config.fp_regs()),
+            int: match config.eval_mode() {
+                EvalMode::Symbolic => IntegerUnit::new(config.int_regs()),
+                EvalMode::Numeric => IntegerUnit::zeroed(config.int_regs()),
+            },
+            rob: config.rob_size().map(ReorderBuffer::new),
+            eu: ExecUnits::new(&config),
+            lsq: LoadStoreQueue::new(&config),
+            cache: config.cache().cloned().map(Cache::new),
+            cdb: Cdb::new(&config),
+            mem: Memory::new(),
+            predictor: config.build_predictor(),
+            config,
+            program: Vec::new(),
+            labels: HashMap::new(),
             insts_comp: Vec::new(),
+            squashed: Vec::new(),
+            events: Vec::new(),
             inst_count: 0,
+            seq: 0,
+            pc: 0,
             cycle: 0,
             finished: false,
-        }
+            branch_pending: false,
+            branches: 0,
+            mispredicts: 0,
+            drained: false,
+        })
     }
 
     /// Add instructions to the executer.
+    ///
+    /// Fails without adding any instruction if one of them uses a
+    /// register the machine does not have, or branches to a label
+    /// which is neither defined by them nor by earlier instructions.
+    pub fn add_insts(&mut self, inst: &[Instruction]) -> Result<(), SimError> {
+        self.check_insts(inst, &HashMap::new())?;
+        self.push_insts(inst);
+        Ok(())
+    }
+
+    fn push_insts(&mut self, inst: &[Instruction]) {
+        for inst in inst.iter() {
+            let mut inst = inst.clone();
+            inst.pc = self.program.len();
+            if let Some(label) = inst.label.as_ref() {
+                self.labels.insert(label.clone(), inst.pc);
+            }
+            self.program.push(inst);
+        }
+    }
+
+    /// Load an assembled program, with its initial memory and registers.
+    ///
+    /// Fails without loading anything if the program uses a register
+    /// the machine does not have or branches to an undefined label.
+    pub fn load(&mut self, program: &Program) -> Result<(), SimError> {
+        for &(id, _) in program.fp_regs.iter() {
+            self.check_reg(id.into(), None)?;
+        }
+        for &(id, _) in program.int_regs.iter() {
+            self.check_reg(id.into(), None)?;
+        }
+        self.check_insts(&program.insts, &program.labels)?;
+        let base = self.program.len();
+        self.push_insts(&program.insts);
+        for (label, pc) in program.labels.iter() {
+            self.labels.insert(label.clone(), base + pc);
+        }
+        for (addr, value) in program.mem.iter() {
+            self.mem.write(addr, value);
+        }
+        for &(id, value) in program.fp_regs.iter() {
+            self.fu.set(id, value);
+        }
+        for &(id, value) in program.int_regs.iter() {
+            self.int.set(id, value);
+        }
+        Ok(())
+    }
+
+    /// Check the instructions before adding them, with the labels
+    /// they define besides their own.
+    fn check_insts(
+        &self,
+        insts: &[Instruction],
+        labels: &HashMap<String, usize>,
+    ) -> Result<(), SimError> {
+        let base = self.program.len();
+        for (i, inst) in insts.iter().enumerate() {
+            self.check_regs(inst, base + i)?;
+            let Some(target) = inst.target.as_ref() else {
+                continue;
+            };
+            let defined = self.labels.contains_key(target)
+                || labels.contains_key(target)
+                || insts.iter().any(|inst| inst.label.as_ref() == Some(target));
+            if !defined {
+                return Err(SimError::UndefinedLabel {
+                    pc: base + i,
+                    label: target.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the machine has every register an instruction uses.
+    fn check_regs(&self, inst: &Instruction, pc: usize) -> Result<(), SimError> {
+        let srcs = [inst.src1.as_ref(), inst.src2.as_ref()]
+            .into_iter()
+            .flatten()
+            .filter_map(|src| match **src {
+                ValueInner::Unit(unit) => Some(unit),
+                _ => None,
+            });
+        for reg in inst.dest.into_iter().chain(srcs) {
+            self.check_reg(reg, Some(pc))?;
+        }
+        Ok(())
+    }
+
+    fn check_reg(&self, reg: Unit, pc: Option<usize>) -> Result<(), SimError> {
+        let exists = match reg {
+            Unit::Fu(id) => self.fu.contains(id),
+            Unit::Regs(id) => self.int.contains(id),
+            Unit::Rs(_) => true,
+        };
+        match exists {
+            true => Ok(()),
+            false => Err(SimError::BadRegister { pc, reg }),
+        }
+    }
+
+    /// Evaluate the current value of a register.
+    pub fn value_of(&self, unit: Unit) -> Option<f64> {
+        let value = match unit {
+            Unit::Fu(id) => self.fu.get(id).value.as_ref(),
+            Unit::Regs(id) => self.int.get(id).value.as_ref(),
+            Unit::Rs(_) => None,
+        };
+        value.and_then(|value| value.eval())
+    }
+
+    /// Use another branch predictor. It is only asked with a reorder
+    /// buffer, since without one issue stalls on every branch.
     #[inline]
-    pub fn add_insts(&mut self, inst: &[Instruction]) {
-        self.insts.extend(inst.iter().cloned());
-        self.inst_count = inst.len();
+    pub fn set_predictor(&mut self, predictor: Box<dyn Predictor>) {
+        self.predictor = predictor;
     }
 
     /// Clear the executer.
     pub fn clear(&mut self) {
         self.rs.clear();
         self.fu.clear();
-        self.insts.clear();
+        self.int.clear();
+        if let Some(rob) = self.rob.as_mut() {
+            rob.clear();
+        }
+        self.predictor = self.config.build_predictor();
+        self.mem.clear();
+        self.program.clear();
+        self.labels.clear();
+        self.insts_comp.clear();
+        self.squashed.clear();
+        self.events.clear();
+        self.inst_count = 0;
+        self.seq = 0;
+        self.pc = 0;
         self.cycle = 0;
         self.finished = false;
+        self.branch_pending = false;
+        self.branches = 0;
+        self.mispredicts = 0;
+        self.drained = false;
+        self.eu.clear();
+        self.lsq.clear();
+        if let Some(cache) = self.cache.as_mut() {
+            cache.clear();
+        }
+        self.cdb.clear();
+        self.stats = Stats::new(&self.config, self.rs.iter());
     }
 
-    /// Run the simulation.
-    pub fn run(&mut self) {
+    /// Run the simulation, printing the state of every cycle.
+    pub fn run(&mut self) -> Result<(), SimError> {
+        let mut renderer = ConsoleRenderer::new(std::io::stdout());
         while !self.finished {
-            println!("{:=^60}", style("=").bold());
-            self.cycle += 1;
+            let report = self.step()?;
+            renderer
+                .render(&report)
+                .map_err(|e| SimError::Output(e.kind()))?;
+        }
+        Ok(())
+    }
+
+    /// Run the simulation to the end without printing anything.
+    pub fn run_to_end(&mut self) -> Result<(), SimError> {
+        while !self.finished {
+            self.cycle(false)?;
+        }
+        Ok(())
+    }
+
+    /// Simulate a single cycle.
+    ///
+    /// Fails once the configured number of cycles has been simulated,
+    /// when an exception is taken, or when a cycle makes no progress:
+    /// nothing is issued, executed, written or committed, so every
+    /// following cycle would be the same.
+    pub fn step(&mut self) -> Result<CycleReport, SimError> {
+        self.cycle(true).map(|report| report.unwrap())
+    }
+
+    /// Simulate a single cycle like [`Executer::step`], but only take
+    /// the snapshot of the last cycle, returning `None` for the others.
+    ///
+    /// Taking a snapshot copies the whole machine, so this is the way
+    /// to run long programs when only the final state is needed.
+    pub fn advance(&mut self) -> Result<Option<CycleReport>, SimError> {
+        self.cycle(false)
+    }
+
+    /// Simulate a cycle, taking a snapshot if `report` is set or
+    /// the program has finished.
+    fn cycle(&mut self, report: bool) -> Result<Option<CycleReport>, SimError> {
+        if self.cycle >= self.config.max_cycles() {
+            return Err(SimError::CycleLimit {
+                cycles: self.config.max_cycles(),
+            });
+        }
+        self.cycle += 1;
+        self.events.clear();
+
+        if self.config.interrupt() == Some(self.cycle) {
+            return Err(self.interrupt());
+        }
+        // commit the instruction at the head of the reorder buffer
+        self.commit()?;
+        // issue new instructions
+        self.issue();
+        // execute instructions
+        let comp = self.exec();
+        // without a reorder buffer exceptions are taken at once
+        if self.rob.is_none() {
+            let faulting = self
+                .rs
+                .iter()
+                .filter(|rs| rs.state == RsState::Ready)
+                .filter_map(|rs| rs.inst())
+                .filter(|inst| inst.exception.is_some())
+                .min_by_key(|inst| inst.seq)
+                .cloned();
+            if let Some(inst) = faulting {
+                return Err(self.fault(inst.exception.unwrap(), Some(inst)));
+            }
+        }
+        // stores write nothing on the CDBs
+        let writes = self
+            .rs
+            .iter()
+            .filter(|rs| comp.contains(&rs.id))
+            .filter(|rs| rs.inst().is_some_and(|inst| inst.op != Type::SD))
+            .count();
+        // write back the result
+        self.write(&comp)?;
 
-            // issue new instructions
-            self.issue();
-            // execute instructions
-            let comp = self.exec();
-            // write back the result
-            self.write(&comp);
+        // check if all instructions are completed
+        self.finished = self.pc >= self.program.len()
+            && !self.branch_pending
+            && self.insts_comp.len() == self.inst_count;
 
-            // check if all instructions are completed
-            self.finished = self.insts_comp.len() == self.inst_count;
+        let report = (report || self.finished).then(|| self.report());
+        self.stats.cycle(self.rs.iter(), writes);
 
-            // print the current state
-            print!("{self:?}");
-            self.print_insts();
-            println!("{:=^60}\n", style("=").bold());
+        // clear the reservation station which has completed instructions
+        self.clear_rs(&comp);
 
-            // clear the reservation station which has completed instructions
-            self.clear_rs(&comp);
+        let executing = self
+            .rs
+            .iter()
+            .any(|rs| matches!(rs.state, RsState::Calculating | RsState::Ready));
+        if !self.finished && !executing && self.events.is_empty() {
+            return Err(self.deadlock());
+        }
 
-            // avoid infinite loop
-            if self.cycle > 1000 {
-                panic!("Cycle limit exceeded. (1000 cycles)");
+        Ok(report)
+    }
+
+    /// Describe why no instruction can make progress.
+    fn deadlock(&self) -> SimError {
+        let waiting = self
+            .rs
+            .iter()
+            .filter(|rs| rs.state == RsState::Busy)
+            .map(|rs| (rs.id, rs.qj.iter().chain(rs.qk.iter()).copied().collect()))
+            .collect();
+        let stalled = match self.program.get(self.pc) {
+            Some(inst)
+                if !self.branch_pending && !self.rob.as_ref().is_some_and(|rob| rob.is_full()) =>
+            {
+                Some(RsType::from(inst.op)).filter(|&t| self.rs.get_free(t).is_none())
             }
+            _ => None,
+        };
+        SimError::Deadlock {
+            cycle: self.cycle,
+            waiting,
+            stalled,
         }
     }
 
-    /// Print the completed instructions.
-    fn print_insts(&mut self) {
-        println!("{}", style("Instructions:").yellow().bold());
-        self.insts_comp.sort_by_key(|i| i.emit_cycle.unwrap_or(0));
-        for inst in self.insts_comp.iter_mut() {
-            println!("{inst}");
+    /// Take the external interrupt.
+    ///
+    /// With a reorder buffer the interrupt is taken before the
+    /// instruction at its head, otherwise before the oldest one
+    /// which has not completed.
+    fn interrupt(&mut self) -> SimError {
+        let inst = match self.rob.as_ref() {
+            Some(rob) => rob.iter().next().map(|entry| entry.inst.clone()),
+            None => self
+                .rs
+                .iter()
+                .filter_map(|rs| rs.inst())
+                .min_by_key(|inst| inst.seq)
+                .cloned(),
+        };
+        let inst = inst.or_else(|| {
+            let mut inst = self.program.get(self.pc).cloned()?;
+            inst.seq = self.seq;
+            Some(inst)
+        });
+        self.fault(Exception::Interrupt, inst)
+    }
+
+    /// Take an exception before `inst`, squashing every instruction
+    /// which has not completed, and describe the state left.
+    fn fault(&mut self, exception: Exception, inst: Option<Instruction>) -> SimError {
+        let seq = inst.as_ref().map_or(self.seq, |inst| inst.seq);
+        let completed = self
+            .insts_comp
+            .iter()
+            .filter(|inst| inst.seq > seq)
+            .cloned()
+            .collect();
+        let mut lost = self
+            .rs
+            .iter()
+            .filter_map(|rs| rs.inst())
+            .chain(self.rob.iter().flat_map(|rob| {
+                let written = rob.iter().filter(|e| e.state == RobState::Written);
+                written.map(|e| &e.inst)
+            }))
+            .filter(|inst| inst.seq < seq)
+            .cloned()
+            .collect::<Vec<_>>();
+        lost.sort_by_key(|inst| inst.seq);
+
+        let fp = self.fu.inner.iter().enumerate().map(|(i, fu)| {
+            let unit = Unit::Fu(FuId::new(i as u8 * 2));
+            (unit, fu.value.clone())
+        });
+        let int = self.int.inner.iter().enumerate().map(|(i, reg)| {
+            let unit = Unit::Regs(RegId::new(i as u8));
+            (unit, reg.value.clone())
+        });
+        let regs = fp.chain(int).collect();
+
+        self.squash();
+        self.rs.clear();
+        self.fu.clear();
+        self.int.clear();
+        self.finished = true;
+        SimError::Exception(Box::new(Fault {
+            exception,
+            cycle: self.cycle,
+            inst,
+            completed,
+            lost,
+            regs,
+            mem: self.mem.clone(),
+        }))
+    }
+
+    /// Take a snapshot of the current state.
+    pub fn report(&self) -> CycleReport {
+        let mut insts = self.insts_comp.clone();
+        if let Some(rob) = self.rob.as_ref() {
+            let written = rob.iter().filter(|e| e.state == RobState::Written);
+            insts.extend(written.map(|e| e.inst.clone()));
+        }
+        insts.extend(self.rs.iter().filter_map(|rs| rs.inst().cloned()));
+        insts.sort_by_key(|inst| inst.seq);
+
+        CycleReport {
+            cycle: self.cycle,
+            finished: self.finished,
+            rs: self.rs.iter().cloned().collect(),
+            units: self.eu.iter().cloned().collect(),
+            mshrs: self
+                .cache
+                .iter()
+                .flat_map(|cache| cache.mshrs())
+                .filter(|mshr| mshr.ready > self.cycle)
+                .cloned()
+                .collect(),
+            rob: self.rob.as_ref().map(|rob| rob.iter().cloned().collect()),
+            fu: self.fu.clone(),
+            int: self.int.clone(),
+            mem: self.mem.clone(),
+            insts,
+            squashed: self.squashed.clone(),
+            events: self.events.clone(),
+            branches: self.branches,
+            mispredicts: self.mispredicts,
         }
     }
 
-    /// Issue instructions to the reservation station.
+    /// The index of the instruction a branch jumps to.
+    ///
+    /// The label was checked when the branch was added.
+    fn target(&self, inst: &Instruction) -> usize {
+        let label = inst.target.as_ref().unwrap();
+        self.labels[label]
+    }
+
+    /// Issue up to the issue width of instructions in program order,
+    /// stopping at the first one which can not be issued.
+    ///
+    /// Each instruction is renamed before the next one reads its
+    /// operands, so an instruction depending on another one issued in
+    /// the same cycle waits for its tag. After a branch predicted taken
+    /// the group continues at the target.
     fn issue(&mut self) {
-        if let Some(inst) = self.insts.pop_front() {
-            if let Some(rs_id) = self.rs.get_free(inst.op.into()) {
-                if let Some(rs) = self.rs.get_mut(rs_id) {
-                    match inst.op {
-                        Type::SD => {
-                            // we do not need to mark the FU as busy when storing
-                            rs.apply(inst, &self.fu, self.cycle);
-                        }
-                        _ => {
-                            if let Unit::Fu(id) = inst.dest {
-                                self.fu.mark_busy(id, rs_id);
-                                rs.apply(inst, &self.fu, self.cycle);
-                            } else {
-                                panic!("Destination of instruction is not a register.")
-                            }
-                        }
-                    }
-                    // only issue one instruction per cycle
-                    return;
+        for _ in 0..self.config.issue_width() {
+            match self.issue_one() {
+                Ok(()) => self.drained = false,
+                Err(Stall::QueueEmpty) if self.drained => break,
+                Err(stall) => {
+                    self.drained = stall == Stall::QueueEmpty;
+                    self.stats.stall(stall);
+                    break;
                 }
             }
-            self.insts.push_front(inst);
         }
     }
 
-    /// Execute instructions in the reservation station.
-    #[inline]
+    /// Issue the next instruction to a reservation station.
+    fn issue_one(&mut self) -> Result<(), Stall> {
+        if self.branch_pending {
+            return Err(Stall::BranchPending);
+        }
+        let Some(mut inst) = self.program.get(self.pc).cloned() else {
+            return Err(Stall::QueueEmpty);
+        };
+        if self.rob.as_ref().is_some_and(|rob| rob.is_full()) {
+            return Err(Stall::RobFull);
+        }
+        let Some(rs_id) = self.rs.get_free(inst.op.into()) else {
+            return Err(Stall::NoFreeRs(inst.op.into()));
+        };
+
+        let mut next = self.pc + 1;
+        if inst.op.is_branch() {
+            if self.rob.is_some() {
+                // speculate on the predicted path
+                let predicted = self.predictor.predict(inst.pc);
+                inst.predicted.replace(predicted);
+                if predicted {
+                    next = self.target(&inst);
+                }
+            } else {
+                self.branch_pending = true;
+            }
+        }
+
+        inst.seq = self.seq;
+        self.seq += 1;
+        self.stats.issued += 1;
+        self.events.push(Event::Issue {
+            seq: inst.seq,
+            rs: rs_id,
+        });
+
+        if let Some(rs) = self.rs.get_mut(rs_id) {
+            let latency = self.config.latency(inst.op);
+            rs.rob = self.rob.as_mut().and_then(|rob| rob.push(inst.clone()));
+            let tag = rs.tag();
+            let dest = inst.dest;
+            let op = inst.op;
+            rs.apply(
+                inst,
+                &self.fu,
+                &self.int,
+                self.rob.as_ref(),
+                self.cycle,
+                latency,
+            );
+            if matches!(op, Type::LD | Type::SD) {
+                self.lsq.push(self.seq - 1, rs_id, op);
+            }
+            // we do not need to mark the FU as busy when storing
+            match dest {
+                Some(Unit::Fu(id)) if op != Type::SD => self.fu.mark_busy(id, tag),
+                Some(Unit::Regs(id)) => self.int.mark_busy(id, tag),
+                _ => {}
+            }
+        }
+
+        self.pc = next;
+        self.inst_count += 1;
+        Ok(())
+    }
+
+    /// Execute instructions in the reservation station, and choose
+    /// the stations which write their result on the CDBs.
     fn exec(&mut self) -> Vec<RsId> {
-        self.rs.exec(self.cycle)
+        self.lsq.update(&self.rs);
+        let mut blocked = self.lsq.blocked();
+        for (id, value) in self.lsq.forwards() {
+            if let Some(rs) = self.rs.get_mut(id) {
+                if rs.inst().is_some_and(|inst| inst.start_cycle.is_none()) {
+                    rs.forwarded.replace(value);
+                }
+            }
+        }
+        for rs in self.rs.iter().filter(|rs| blocked.contains(&rs.id)) {
+            if let Some(inst) = rs.inst() {
+                if rs.state == RsState::Calculating && inst.start_cycle.is_none() {
+                    self.events.push(Event::MemoryWait {
+                        seq: inst.seq,
+                        rs: rs.id,
+                    });
+                    self.stats.mem_waits += 1;
+                }
+            }
+        }
+        blocked.extend(self.mshr_full(&blocked));
+        let waiting = self.rs.exec(self.cycle, &self.mem, &mut self.eu, &blocked);
+        self.read_cache();
+        for rs in self.rs.iter().filter(|rs| waiting.contains(&rs.id)) {
+            if let Some(inst) = rs.inst() {
+                self.events.push(Event::NoFreeUnit {
+                    seq: inst.seq,
+                    rs: rs.id,
+                });
+                self.stats.unit_wait(inst.op.into());
+            }
+        }
+        for (rs, inst) in self
+            .rs
+            .iter()
+            .filter_map(|rs| rs.inst().map(|inst| (rs, inst)))
+        {
+            if inst.start_cycle == Some(self.cycle) {
+                self.events.push(Event::ExecStart { seq: inst.seq });
+            }
+            if inst.exec_cycle == Some(self.cycle) && rs.forwarded.is_some() {
+                self.events.push(Event::Forward { seq: inst.seq });
+                self.stats.forwarded += 1;
+            }
+            if inst.exec_cycle == Some(self.cycle) {
+                self.events.push(Event::ExecComplete { seq: inst.seq });
+            }
+        }
+
+        let ready = self
+            .rs
+            .iter()
+            .filter(|rs| rs.state == RsState::Ready)
+            .collect::<Vec<_>>();
+        let (comp, delayed) = self.cdb.arbitrate(&ready);
+        for rs in ready.iter().filter(|rs| delayed.contains(&rs.id)) {
+            let seq = rs.inst().map_or(0, |inst| inst.seq);
+            self.events.push(Event::CdbConflict { seq, rs: rs.id });
+        }
+        self.stats.cdb_conflicts += delayed.len() as u64;
+        comp
+    }
+
+    /// The loads which can not start because they would miss in the
+    /// data cache while every MSHR is taken.
+    ///
+    /// Loads are considered in program order, each new block missed
+    /// taking one of the free registers.
+    fn mshr_full(&mut self, blocked: &[RsId]) -> Vec<RsId> {
+        let Some(cache) = self.cache.as_ref() else {
+            return Vec::new();
+        };
+        let Some(mut free) = cache.free_mshrs(self.cycle) else {
+            return Vec::new();
+        };
+        let (mut full, mut missed) = (Vec::new(), Vec::new());
+        for entry in self.lsq.iter().filter(|entry| entry.op == Type::LD) {
+            let Some(rs) = self.rs.iter().find(|rs| rs.id == entry.rs) else {
+                continue;
+            };
+            let Some(inst) = rs.inst() else {
+                continue;
+            };
+            if rs.state != RsState::Calculating
+                || inst.start_cycle.is_some()
+                || rs.forwarded.is_some()
+                || blocked.contains(&rs.id)
+            {
+                continue;
+            }
+            let Some(addr) = entry.addr.as_ref().and_then(|addr| addr.eval()) else {
+                continue;
+            };
+            match cache.primary_miss(addr as i64, self.cycle) {
+                Some(block) if missed.contains(&block) => {}
+                Some(_) if free == 0 => {
+                    self.events.push(Event::MshrFull {
+                        seq: inst.seq,
+                        rs: rs.id,
+                    });
+                    self.stats.mshr_waits += 1;
+                    full.push(rs.id);
+                }
+                Some(block) => {
+                    free -= 1;
+                    missed.push(block);
+                }
+                None => {}
+            }
+        }
+        full
+    }
+
+    /// Look up the loads which started executing in the data cache,
+    /// delaying the ones which miss, and their execution unit.
+    ///
+    /// Forwarded loads and loads from a symbolic address do not access
+    /// the cache.
+    fn read_cache(&mut self) {
+        let Some(cache) = self.cache.as_mut() else {
+            return;
+        };
+        for entry in self.lsq.iter().filter(|entry| entry.op == Type::LD) {
+            let Some(rs) = self.rs.get_mut(entry.rs) else {
+                continue;
+            };
+            if rs.forwarded.is_some() {
+                continue;
+            }
+            let Some(addr) = entry.addr.as_ref().and_then(|addr| addr.eval()) else {
+                continue;
+            };
+            let Some(inst) = rs
+                .inst_mut()
+                .filter(|inst| inst.start_cycle == Some(self.cycle))
+            else {
+                continue;
+            };
+            let (access, penalty) = cache.read(addr as i64, self.cycle);
+            inst.delay(penalty);
+            self.eu.delay(entry.rs, penalty);
+            self.stats.cache_access(access);
+            if !access.hit {
+                self.events.push(Event::CacheMiss { seq: inst.seq });
+            }
+        }
     }
 
     /// Write the result back from the reservation station to the FU.
     ///
     /// This will also broadcast the result to the other reservation stations.
-    fn write(&mut self, comp: &Vec<RsId>) {
+    /// With a reorder buffer the result goes to its entry instead of the FU,
+    /// which is updated when the instruction commits. Nothing is written if
+    /// a branch can not be decided.
+    fn write(&mut self, comp: &Vec<RsId>) -> Result<(), SimError> {
+        // a branch on a condition which is not a number can not be resolved
+        for rs in self.rs.iter().filter(|rs| comp.contains(&rs.id)) {
+            let (Some(inst), Some(value)) = (rs.inst(), rs.result()) else {
+                continue;
+            };
+            if inst.op.is_branch() && value.eval().is_none() {
+                return Err(SimError::UndecidedBranch {
+                    pc: inst.pc,
+                    condition: value.to_string(),
+                });
+            }
+        }
         let mut boardcast = Vec::new();
         for rs_id in comp {
-            if let Some(rs) = self.rs.get_mut(*rs_id) {
-                if let Some(Unit::Fu(fu_id)) = rs.dest() {
-                    let value = rs.result().unwrap();
-                    boardcast.push((*rs_id, value.clone()));
-                    self.fu.mark_ready(*fu_id, rs.id, value);
-                    let mut inst = rs.take().unwrap();
-                    inst.write(self.cycle);
+            let Some(rs) = self.rs.get_mut(*rs_id) else {
+                continue;
+            };
+            let tag = rs.tag();
+            let rob_id = rs.rob;
+            let value = rs.result().unwrap();
+            let addr = rs.addr.clone();
+            let mut inst = rs.take().unwrap();
+            inst.write(self.cycle);
+            if inst.op == Type::LD || (inst.op == Type::SD && rob_id.is_none()) {
+                self.lsq.remove(inst.seq);
+            }
+            if inst.op.is_branch() {
+                inst.taken.replace(value.eval() != Some(0.0));
+            }
+            boardcast.push((tag, value.clone()));
+            self.events.push(Event::Write { seq: inst.seq, tag });
+
+            match (self.rob.as_mut(), rob_id) {
+                (Some(rob), Some(rob_id)) => rob.write(rob_id, inst, value, addr),
+                _ => {
+                    match inst.dest {
+                        _ if inst.op.is_branch() => self.resolve(&inst),
+                        _ if inst.op == Type::SD => self.store(addr.as_ref(), &value),
+                        Some(Unit::Fu(id)) if inst.op != Type::SD => {
+                            self.fu.mark_ready(id, tag, value)
+                        }
+                        Some(Unit::Regs(id)) => self.int.mark_ready(id, tag, value),
+                        _ => {}
+                    }
+                    self.stats.complete(&inst);
                     self.insts_comp.push(inst);
                 }
             }
         }
-        for (rs_id, value) in boardcast {
-            self.rs.flush(rs_id, value);
+        for (tag, value) in boardcast {
+            self.rs.flush(tag, value);
         }
+        Ok(())
+    }
+
+    /// Resolve a branch without a reorder buffer and resume issuing.
+    fn resolve(&mut self, inst: &Instruction) {
+        self.pc = match inst.taken {
+            Some(true) => self.target(inst),
+            _ => inst.pc + 1,
+        };
+        self.branch_pending = false;
+        self.branches += 1;
+    }
+
+    /// Commit the instruction at the head of the reorder buffer.
+    ///
+    /// Only instructions whose result has been written in a
+    /// previous cycle can be committed. A mispredicted branch
+    /// squashes all the instructions after it, and an instruction
+    /// which raised an exception is not committed: the exception is
+    /// taken, squashing it and all the instructions after it.
+    fn commit(&mut self) -> Result<(), SimError> {
+        let Some(mut entry) = self.rob.as_mut().and_then(|rob| rob.commit()) else {
+            return Ok(());
+        };
+        if let Some(exception) = entry.inst.exception {
+            return Err(self.fault(exception, Some(entry.inst)));
+        }
+
+        if let Some(value) = entry.value {
+            match entry.inst.dest {
+                _ if entry.inst.op == Type::SD => {
+                    self.store(entry.addr.as_ref(), &value);
+                    self.lsq.remove(entry.inst.seq);
+                }
+                Some(Unit::Fu(id)) if entry.inst.op != Type::SD => {
+                    self.fu.commit(id, entry.id, value)
+                }
+                Some(Unit::Regs(id)) => self.int.commit(id, entry.id, value),
+                _ => {}
+            }
+        }
+        entry.inst.commit(self.cycle);
+        self.events.push(Event::Commit {
+            seq: entry.inst.seq,
+        });
+
+        if entry.inst.op.is_branch() {
+            let taken = entry.inst.taken == Some(true);
+            self.predictor.update(entry.inst.pc, taken);
+            self.branches += 1;
+            if entry.inst.predicted != Some(taken) {
+                self.mispredicts += 1;
+                self.events.push(Event::Mispredict {
+                    seq: entry.inst.seq,
+                });
+                self.squash();
+                self.pc = match taken {
+                    true => self.target(&entry.inst),
+                    false => entry.inst.pc + 1,
+                };
+            }
+        }
+
+        self.stats.complete(&entry.inst);
+        self.insts_comp.push(entry.inst);
+        Ok(())
+    }
+
+    /// Write the memory with the value of a store.
+    ///
+    /// Only numbers are kept in memory, so symbolic stores leave it unchanged.
+    /// Stores to a numeric address also go through the data cache.
+    fn store(&mut self, addr: Option<&Value>, value: &Value) {
+        let Some(addr) = addr.and_then(|addr| addr.eval()) else {
+            return;
+        };
+        if let Some(value) = value.eval() {
+            self.mem.write(addr as i64, value);
+        }
+        if let Some(cache) = self.cache.as_mut() {
+            let access = cache.write(addr as i64, self.cycle);
+            self.stats.cache_access(access);
+        }
+    }
+
+    /// Squash all the speculative instructions.
+    fn squash(&mut self) {
+        if let Some(rob) = self.rob.as_mut() {
+            self.inst_count -= rob.len();
+            self.events.push(Event::Squash { count: rob.len() });
+            // the station has the timing of the ones not written yet
+            let in_rs = self
+                .rs
+                .iter()
+                .filter_map(|rs| rs.inst())
+                .collect::<Vec<_>>();
+            for entry in rob.iter() {
+                let seq = entry.inst.seq;
+                let in_rs = in_rs.iter().find(|inst| inst.seq == seq);
+                let mut inst = in_rs.copied().unwrap_or(&entry.inst).clone();
+                inst.squash_cycle = Some(self.cycle);
+                self.squashed.push(inst);
+            }
+            rob.clear();
+        }
+        self.rs.squash();
+        self.lsq.clear();
+        self.fu.squash();
+        self.int.squash();
     }
 
     /// Clear the reservation station.
@@ -150,25 +926,892 @@ impl Executer {
     }
 }
 
-impl std::fmt::Debug for Executer {
+impl std::fmt::Display for SimError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let finished = if self.finished {
-            style("> Finished").green().bold()
-        } else {
-            style("> Running").red().bold()
-        };
-
-        writeln!(
-            f,
-            "{} {} {}",
-            style("Cycle:").yellow().bold(),
-            style(self.cycle).cyan().bold(),
-            finished
-        )?;
-        writeln!(f, "{}", style("Reservation Stations:").yellow().bold())?;
-        writeln!(f, "{:?}", self.rs)?;
-        writeln!(f, "{}", style("Floating Units:").yellow().bold())?;
-        writeln!(f, "{:?}", self.fu)?;
-        Ok(())
+        match self {
+            SimError::Deadlock {
+                cycle,
+                waiting,
+                stalled,
+            } => {
+                write!(f, "deadlock at cycle {cycle}")?;
+                for (id, tags) in waiting.iter() {
+                    let tags = tags.iter().map(|tag| tag.name()).collect::<Vec<_>>();
+                    write!(f, "\n  {} waits on {}", id.name(), tags.join(", "))?;
+                }
+                if let Some(rs_type) = stalled {
+                    write!(f, "\n  issue stalls on a free {rs_type:?} station")?;
+                }
+                Ok(())
+            }
+            SimError::CycleLimit { cycles } => write!(f, "cycle limit exceeded ({cycles} cycles)"),
+            SimError::Exception(fault) => write!(f, "{fault}"),
+            SimError::BadRegister { pc, reg } => {
+                write!(f, "the machine has no register {reg}")?;
+                match pc {
+                    Some(pc) => write!(f, ", used by the instruction at pc {pc}"),
+                    None => write!(f, ", given an initial value"),
+                }
+            }
+            SimError::UndefinedLabel { pc, label } => {
+                write!(
+                    f,
+                    "undefined label `{label}`, used by the branch at pc {pc}"
+                )
+            }
+            SimError::UndecidedBranch { pc, condition } => write!(
+                f,
+                "can not decide the branch at pc {pc}, its condition {condition} is not a number"
+            ),
+            SimError::Output(kind) => write!(f, "can not print the cycle: {kind}"),
+        }
+    }
+}
+
+impl std::error::Error for SimError {}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::tomasulo::parser::parse_program;
+
+    const PROGRAM: &str = r"
+        LD F2 0 R2
+        LD F4 0 R3
+        DIVD F0 F4 F2
+        MULTD F6 F0 F2
+        ADDD F0 F4 F2
+        SD F6 0 R3
+        MULTD F6 F0 F2
+        SD F6 0 R1";
+
+    fn parse(program: &str) -> Vec<Instruction> {
+        program
+            .lines()
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| s.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_rob_commit_in_order() {
+        let mut executer = Executer::new(MachineConfig::default().with_rob_size(6)).unwrap();
+        executer.add_insts(&parse(PROGRAM)).unwrap();
+        executer.run_to_end().unwrap();
+
+        let mut last = 0;
+        for inst in executer.insts_comp.iter() {
+            let commit = inst.commit_cycle.unwrap();
+            assert!(commit > inst.write_cycle.unwrap());
+            assert!(commit > last);
+            last = commit;
+        }
+        assert_eq!(executer.insts_comp.len(), 8);
+    }
+
+    const LOOP: &str = r"
+        Loop: LD F0 0 R1
+        ADDD F4 F0 F2
+        SD F4 0 R1
+        SUBD F8 F8 F2
+        BNEZ F8 Loop";
+
+    #[test]
+    fn test_branch_loop() {
+        let configs = [
+            MachineConfig::default(),
+            MachineConfig::default().with_rob_size(8),
+            MachineConfig::default()
+                .with_rob_size(8)
+                .with_predictor(PredictorKind::TwoBit),
+        ];
+        for config in configs {
+            let mut executer = Executer::new(config).unwrap();
+            executer.add_insts(&parse(LOOP)).unwrap();
+            executer.run_to_end().unwrap();
+
+            // F8 counts down from 8 by 2
+            assert_eq!(executer.insts_comp.len(), 20);
+            assert_eq!(executer.branches, 4);
+        }
+    }
+
+    #[test]
+    fn test_word_array() {
+        // sum the words from the last one down
+        let program = parse_program(
+            r"
+            .data 0
+            A:  .word 1, 2, 3
+            .text
+                .reg R1, 24
+                .reg F2, 0
+            Loop:
+                LD F0, A-8(R1)
+                ADDD F2, F2, F0
+                DADDUI R1, R1, #-8
+                BNEZ R1, Loop",
+        )
+        .unwrap();
+        let config = MachineConfig::default().with_eval_mode(EvalMode::Numeric);
+        let mut executer = Executer::new(config).unwrap();
+        executer.load(&program).unwrap();
+        executer.run_to_end().unwrap();
+
+        assert_eq!(executer.mem.read(8), 2.0);
+        assert_eq!(executer.branches, 3);
+        assert_eq!(executer.value_of(Unit::Fu(FuId::new(2))), Some(6.0));
+    }
+
+    #[test]
+    fn test_zero_register() {
+        let program = r"
+            DADDI R0 R0 #5
+            DADDI R1 R0 #1";
+        for config in [
+            MachineConfig::default(),
+            MachineConfig::default().with_rob_size(8),
+            MachineConfig::default().with_eval_mode(EvalMode::Numeric),
+        ] {
+            let mut executer = Executer::new(config).unwrap();
+            executer.int.set(RegId::new(0), 3);
+            executer.add_insts(&parse(program)).unwrap();
+            executer.run_to_end().unwrap();
+
+            assert_eq!(executer.value_of(Unit::Regs(RegId::new(0))), Some(0.0));
+            assert_eq!(executer.value_of(Unit::Regs(RegId::new(1))), Some(1.0));
+            assert!(executer.int.get(RegId::new(0)).qi.is_none());
+        }
+    }
+
+    #[test]
+    fn test_integer_loop() {
+        let program = r"
+            Loop: LD F0 0 R1
+            ADDD F4 F0 F2
+            SD F4 0 R1
+            DADDUI R1 R1 #-8
+            BNEZ R1 Loop";
+        for config in [
+            MachineConfig::default(),
+            MachineConfig::default().with_rob_size(8),
+        ] {
+            let mut executer = Executer::new(config).unwrap();
+            executer.int.set(RegId::new(1), 24);
+            executer.add_insts(&parse(program)).unwrap();
+            executer.run_to_end().unwrap();
+
+            assert_eq!(executer.insts_comp.len(), 15);
+            assert_eq!(
+                executer
+                    .int
+                    .get(RegId::new(1))
+                    .value
+                    .as_ref()
+                    .unwrap()
+                    .eval(),
+                Some(0.0)
+            );
+
+            // the loads after the first one wait for the address of DADDUI
+            let daddui = executer.insts_comp.iter().filter(|i| i.op == Type::DADDUI);
+            let loads = executer
+                .insts_comp
+                .iter()
+                .filter(|i| i.op == Type::LD)
+                .skip(1);
+            for (daddui, ld) in daddui.zip(loads) {
+                assert!(ld.start_cycle > daddui.write_cycle);
+            }
+        }
+    }
+
+    #[test]
+    fn test_numeric_sum() {
+        let program = r"
+            Loop: LD F0 -8 R1
+            ADDD F4 F4 F0
+            DADDUI R1 R1 #-8
+            BNEZ R1 Loop
+            SD F4 0 R2";
+        for config in [
+            MachineConfig::default(),
+            MachineConfig::default().with_rob_size(8),
+        ] {
+            let mut executer = Executer::new(config.with_eval_mode(EvalMode::Numeric)).unwrap();
+            executer.mem.write(0, 1.5);
+            executer.mem.write(8, 2.5);
+            executer.mem.write(16, 4.0);
+            executer.int.set(RegId::new(1), 24);
+            executer.int.set(RegId::new(2), 100);
+            executer.fu.set(FuId::new(4), 0.5);
+            executer.add_insts(&parse(program)).unwrap();
+            executer.run_to_end().unwrap();
+
+            assert_eq!(executer.value_of(Unit::Fu(FuId::new(4))), Some(8.5));
+            assert_eq!(executer.value_of(Unit::Regs(RegId::new(1))), Some(0.0));
+            assert_eq!(executer.mem.read(100), 8.5);
+        }
+    }
+
+    #[test]
+    fn test_load_program() {
+        let program = parse_program(
+            r"
+            .data 100
+            A:  .double 1.5, 2.5
+            .text
+            .reg R1, 16
+            .reg F2, 0.5
+            Loop:
+                LD F0, A-8(R1)
+                ADDD F4, F0, F2
+                SD F4, A-8(R1)
+                DADDUI R1, R1, #-8
+                BNEZ R1, Loop",
+        )
+        .unwrap();
+        let config = MachineConfig::default().with_eval_mode(EvalMode::Numeric);
+        let mut executer = Executer::new(config).unwrap();
+        executer.load(&program).unwrap();
+        executer.run_to_end().unwrap();
+
+        assert_eq!(executer.mem.read(100), 2.0);
+        assert_eq!(executer.mem.read(108), 3.0);
+        assert_eq!(executer.value_of(Unit::Regs(RegId::new(1))), Some(0.0));
+    }
+
+    #[test]
+    fn test_memory_order() {
+        let program = parse_program(
+            r"
+            .reg R1, 100
+            .reg F2, 1.5
+            ADDD F4, F2, F2
+            SD F4, 0(R1)
+            LD F6, 0(R1)
+            LD F8, 8(R1)",
+        )
+        .unwrap();
+        let mut starts = Vec::new();
+        for policy in [LsqPolicy::Address, LsqPolicy::Conservative] {
+            let config = MachineConfig::default()
+                .with_eval_mode(EvalMode::Numeric)
+                .with_lsq_policy(policy);
+            let mut executer = Executer::new(config).unwrap();
+            executer.load(&program).unwrap();
+            executer.run_to_end().unwrap();
+
+            // the load reads the value stored before it
+            assert_eq!(executer.value_of(Unit::Fu(FuId::new(6))), Some(3.0));
+            let store = executer.insts_comp.iter().find(|i| i.op == Type::SD);
+            let load = executer.insts_comp.iter().find(|i| i.seq == 2);
+            assert!(load.unwrap().start_cycle > store.unwrap().write_cycle);
+            let other = executer.insts_comp.iter().find(|i| i.seq == 3);
+            starts.push(other.unwrap().start_cycle.unwrap());
+            assert!(executer.stats.mem_waits > 0);
+        }
+        // only the conservative policy delays the load from another address
+        assert!(starts[0] < starts[1]);
+    }
+
+    #[test]
+    fn test_forwarding() {
+        let program = parse_program(
+            r"
+            .reg R1, 100
+            .reg F2, 1.5
+            ADDD F4, F2, F2
+            SD F4, 0(R1)
+            LD F6, 0(R1)",
+        )
+        .unwrap();
+        for config in [
+            MachineConfig::default(),
+            MachineConfig::default().with_rob_size(8),
+        ] {
+            let config = config.with_eval_mode(EvalMode::Numeric);
+            let mut start = Vec::new();
+            for forwarding in [false, true] {
+                let mut executer =
+                    Executer::new(config.clone().with_forwarding(forwarding)).unwrap();
+                executer.load(&program).unwrap();
+                executer.run_to_end().unwrap();
+
+                assert_eq!(executer.value_of(Unit::Fu(FuId::new(6))), Some(3.0));
+                assert_eq!(executer.stats.forwarded, forwarding as u64);
+                let load = executer.insts_comp.iter().find(|i| i.op == Type::LD);
+                start.push(load.unwrap().start_cycle.unwrap());
+            }
+            // the load starts once the ADDD has written the value to store
+            assert_eq!(start[1], 5);
+            assert!(start[0] > start[1]);
+        }
+    }
+
+    #[test]
+    fn test_symbolic_exceptions() {
+        // the divisor is a number even without numeric evaluation
+        let mut executer = Executer::new(MachineConfig::default().with_mem_align(8)).unwrap();
+        executer
+            .load(&parse_program(".reg F2, 0\nDIVD F0, F4, F2").unwrap())
+            .unwrap();
+        let Err(SimError::Exception(fault)) = executer.run_to_end() else {
+            panic!("expected an exception");
+        };
+        assert_eq!(fault.exception, Exception::DivideByZero);
+
+        let mut executer = Executer::new(MachineConfig::default().with_mem_align(8)).unwrap();
+        executer
+            .load(&parse_program(".reg R1, 4\nLD F2, 0(R1)").unwrap())
+            .unwrap();
+        let Err(SimError::Exception(fault)) = executer.run_to_end() else {
+            panic!("expected an exception");
+        };
+        assert_eq!(fault.exception, Exception::MisalignedAddress(4));
+
+        // a loaded divisor is an expression which can not be checked
+        let mut executer = Executer::new(MachineConfig::default()).unwrap();
+        executer
+            .load(&parse_program("LD F2, 0(R1)\nDIVD F0, F4, F2").unwrap())
+            .unwrap();
+        executer.run_to_end().unwrap();
+    }
+
+    #[test]
+    fn test_exceptions() {
+        let program = parse_program(
+            r"
+            .reg F2, 0
+            .reg F4, 6
+            DIVD F0, F4, F2
+            ADDD F6, F4, F4",
+        )
+        .unwrap();
+        let fault = |config: MachineConfig| {
+            let mut executer = Executer::new(config.with_eval_mode(EvalMode::Numeric)).unwrap();
+            executer.load(&program).unwrap();
+            match executer.run_to_end() {
+                Err(SimError::Exception(fault)) => fault,
+                result => panic!("expected an exception, got {result:?}"),
+            }
+        };
+        let f6 = |fault: &Fault| {
+            let unit = Unit::Fu(FuId::new(6));
+            let value = fault.regs.iter().find(|(u, _)| *u == unit);
+            value.and_then(|(_, value)| value.as_ref()?.eval())
+        };
+
+        // the ADDD has already written F6 when the division faults
+        let imprecise = fault(MachineConfig::default());
+        assert_eq!(imprecise.exception, Exception::DivideByZero);
+        assert_eq!(imprecise.inst.as_ref().unwrap().op, Type::DIVD);
+        assert_eq!(imprecise.completed.len(), 1);
+        assert!(!imprecise.is_precise());
+        assert_eq!(f6(&imprecise), Some(12.0));
+
+        // but its result is squashed before it commits
+        let precise = fault(MachineConfig::default().with_rob_size(8));
+        assert_eq!(precise.exception, Exception::DivideByZero);
+        assert!(precise.is_precise());
+        assert_eq!(f6(&precise), Some(6.0));
+        assert!(precise.cycle > imprecise.cycle);
+
+        // the ADDD has completed but the DIVD has not
+        let interrupt = fault(MachineConfig::default().with_interrupt(10));
+        assert_eq!(interrupt.exception, Exception::Interrupt);
+        assert_eq!(interrupt.inst.as_ref().unwrap().op, Type::DIVD);
+        assert!(!interrupt.is_precise());
+        let interrupt = fault(MachineConfig::default().with_rob_size(8).with_interrupt(10));
+        assert!(interrupt.is_precise());
+        assert_eq!(f6(&interrupt), Some(6.0));
+
+        let program = parse_program(
+            r"
+            .reg R1, 100
+            LD F2, 3(R1)",
+        )
+        .unwrap();
+        let mut executer = Executer::new(
+            MachineConfig::default()
+                .with_eval_mode(EvalMode::Numeric)
+                .with_mem_align(8),
+        )
+        .unwrap();
+        executer.load(&program).unwrap();
+        let Err(SimError::Exception(fault)) = executer.run_to_end() else {
+            panic!("expected an exception");
+        };
+        assert_eq!(fault.exception, Exception::MisalignedAddress(103));
+
+        let mut executer = Executer::new(
+            MachineConfig::default()
+                .with_eval_mode(EvalMode::Numeric)
+                .with_mem_size(64),
+        )
+        .unwrap();
+        executer.load(&program).unwrap();
+        let Err(SimError::Exception(fault)) = executer.run_to_end() else {
+            panic!("expected an exception");
+        };
+        assert_eq!(fault.exception, Exception::AddressOutOfRange(103));
+        assert!(executer.finished);
+    }
+
+    #[test]
+    fn test_cache() {
+        let program = parse_program(
+            r"
+            .reg R1, 100
+            LD F2, 0(R1)
+            LD F4, 8(R1)
+            SD F2, 16(R1)",
+        )
+        .unwrap();
+        let config = MachineConfig::default()
+            .with_eval_mode(EvalMode::Numeric)
+            .with_cache(CacheConfig::default());
+        let mut executer = Executer::new(config).unwrap();
+        executer.load(&program).unwrap();
+        executer.run_to_end().unwrap();
+
+        let timing = |seq: usize| {
+            let inst = executer.insts_comp.iter().find(|i| i.seq == seq).unwrap();
+            (inst.start_cycle.unwrap(), inst.exec_cycle.unwrap())
+        };
+        // the first load misses, the second one waits for the same block
+        // and the store hits once it arrived
+        assert_eq!(timing(0), (2, 13));
+        assert_eq!(timing(1), (3, 13));
+        assert_eq!(executer.stats.cache_hits, 1);
+        assert_eq!(executer.stats.cache_misses, 2);
+        assert_eq!(executer.stats.mshr_merges, 1);
+        assert_eq!(executer.stats.cache_hit_rate(), Some(1.0 / 3.0));
+    }
+
+    #[test]
+    fn test_cache_holds_unit() {
+        let program = parse_program(".reg R1, 0\nLD F2, 0(R1)\nLD F4, 64(R1)").unwrap();
+        let config = MachineConfig::default()
+            .with_eval_mode(EvalMode::Numeric)
+            .with_cache(CacheConfig::default())
+            .with_eu_count(EuType::Load, 1);
+        let mut executer = Executer::new(config).unwrap();
+        executer.load(&program).unwrap();
+        executer.run_to_end().unwrap();
+
+        // the only load unit is busy until the block of the first
+        // load arrives
+        let first = executer.insts_comp.iter().find(|i| i.seq == 0).unwrap();
+        let second = executer.insts_comp.iter().find(|i| i.seq == 1).unwrap();
+        assert_eq!((first.start_cycle, first.exec_cycle), (Some(2), Some(13)));
+        assert_eq!(second.start_cycle, Some(14));
+    }
+
+    #[test]
+    fn test_mshrs() {
+        let program = parse_program(
+            r"
+            .reg R1, 0
+            LD F2, 0(R1)
+            LD F4, 8(R1)
+            LD F6, 64(R1)
+            LD F8, 128(R1)",
+        )
+        .unwrap();
+        let run = |mshrs: usize| {
+            let cache = CacheConfig {
+                mshrs: Some(mshrs),
+                ..CacheConfig::default()
+            };
+            let config = MachineConfig::default()
+                .with_eval_mode(EvalMode::Numeric)
+                .with_cache(cache);
+            let mut executer = Executer::new(config).unwrap();
+            executer.load(&program).unwrap();
+            executer.run_to_end().unwrap();
+            executer
+        };
+
+        let single = run(1);
+        // the second load merges into the miss of the first one
+        assert_eq!(single.stats.cache_misses, 4);
+        assert_eq!(single.stats.mshr_merges, 1);
+        let second = single.insts_comp.iter().find(|i| i.seq == 1).unwrap();
+        assert_eq!((second.start_cycle, second.exec_cycle), (Some(3), Some(13)));
+        // the other loads wait for the register
+        assert!(single.stats.mshr_waits > 0);
+        let third = single.insts_comp.iter().find(|i| i.seq == 2).unwrap();
+        assert_eq!(third.start_cycle, Some(12));
+
+        // more registers overlap the misses
+        let double = run(2);
+        assert!(double.stats.mshr_waits < single.stats.mshr_waits);
+        assert!(double.cycle < single.cycle);
+    }
+
+    #[test]
+    fn test_sim_errors() {
+        let mut executer = Executer::new(MachineConfig::default().with_max_cycles(5)).unwrap();
+        executer.add_insts(&parse(PROGRAM)).unwrap();
+        assert_eq!(
+            executer.run_to_end(),
+            Err(SimError::CycleLimit { cycles: 5 })
+        );
+        assert_eq!(executer.cycle, 5);
+
+        // no multiplier station to issue to
+        let config = MachineConfig::default().with_rs_count(RsType::MULT, 0);
+        let mut executer = Executer::new(config).unwrap();
+        executer
+            .add_insts(&parse("ADDD F0 F2 F4\nMULTD F6 F0 F2"))
+            .unwrap();
+        assert_eq!(
+            executer.run_to_end(),
+            Err(SimError::Deadlock {
+                cycle: 5,
+                waiting: Vec::new(),
+                stalled: Some(RsType::MULT),
+            })
+        );
+
+        // waiting on a result which is never written
+        let mut executer = Executer::new(MachineConfig::default()).unwrap();
+        let mult = executer.rs.get_free(RsType::MULT).unwrap();
+        executer.fu.mark_busy(FuId::new(2), mult.into());
+        executer.add_insts(&parse("ADDD F0 F2 F4")).unwrap();
+        let Err(SimError::Deadlock { cycle, waiting, .. }) = executer.run_to_end() else {
+            panic!("expected a deadlock");
+        };
+        assert_eq!(cycle, 2);
+        assert_eq!(waiting.len(), 1);
+        assert_eq!(waiting[0].1, [Tag::Rs(mult)]);
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut executer = Executer::new(MachineConfig::default()).unwrap();
+        executer.add_insts(&parse(PROGRAM)).unwrap();
+        executer.run_to_end().unwrap();
+        let stats = &executer.stats;
+
+        assert_eq!(stats.cycles, executer.cycle);
+        assert_eq!(stats.issued, 8);
+        assert_eq!(stats.completed, 8);
+        assert_eq!(stats.cpi(), Some(executer.cycle as f64 / 8.0));
+        // the two stores do not use the CDB
+        assert_eq!(stats.cdb_writes, 6);
+        // both loads take 2 cycles, written in the cycle after
+        assert_eq!(stats.avg_latency(Type::LD), Some(3.0));
+        assert_eq!(stats.rs_count[&RsType::MULT], 2);
+        assert!(stats.occupancy(RsType::MULT).unwrap() > stats.occupancy(RsType::ADD).unwrap());
+        // the second MULTD waits for a free multiplier
+        assert!(stats.stalls[&Stall::NoFreeRs(RsType::MULT)] > 0);
+        assert!(stats.to_string().contains("CPI"));
+    }
+
+    #[test]
+    fn test_stall_breakdown() {
+        let mut executer = Executer::new(MachineConfig::default()).unwrap();
+        executer
+            .add_insts(&parse("MULTD F2 F0 F0\nMULTD F4 F0 F0\nMULTD F6 F0 F0"))
+            .unwrap();
+        executer.run_to_end().unwrap();
+
+        // the third MULTD waits from cycle 3 until the first one frees
+        // its station after writing in cycle 12, and running out of
+        // instructions is counted once however long the tail is
+        assert_eq!(
+            executer.stats.stalls,
+            BTreeMap::from([(Stall::NoFreeRs(RsType::MULT), 10), (Stall::QueueEmpty, 1),])
+        );
+        assert!(executer.cycle > 20);
+    }
+
+    #[test]
+    fn test_cdb_conflict() {
+        let program = parse(
+            r"
+            ADDD F2 F0 F0
+            ADDD F4 F0 F0",
+        );
+        let config = MachineConfig::default().with_issue_width(2);
+        let mut unlimited = Executer::new(config.clone()).unwrap();
+        unlimited.add_insts(&program).unwrap();
+        unlimited.run_to_end().unwrap();
+        let write = |e: &Executer, i: usize| e.insts_comp[i].write_cycle.unwrap();
+        assert_eq!(write(&unlimited, 0), write(&unlimited, 1));
+
+        let mut executer = Executer::new(config.with_cdb_count(1)).unwrap();
+        executer.add_insts(&program).unwrap();
+        let mut conflicts = Vec::new();
+        while !executer.finished {
+            let report = executer.step().unwrap();
+            conflicts.extend(report.events.into_iter().filter_map(|e| match e {
+                Event::CdbConflict { seq, .. } => Some(seq),
+                _ => None,
+            }));
+        }
+        // the younger ADDD waits a cycle for the bus
+        assert_eq!(conflicts, vec![1]);
+        assert_eq!(write(&executer, 0), write(&unlimited, 0));
+        assert_eq!(write(&executer, 1), write(&unlimited, 1) + 1);
+        assert_eq!(executer.stats.cdb_conflicts, 1);
+        assert_eq!(executer.stats.cdb_writes, 2);
+    }
+
+    #[test]
+    fn test_exec_units() {
+        let config = MachineConfig::default()
+            .with_issue_width(2)
+            .with_eu_count(EuType::Adder, 1);
+        let mut executer = Executer::new(config).unwrap();
+        executer
+            .add_insts(&parse(
+                r"
+            ADDD F2 F0 F0
+            ADDD F4 F0 F0",
+            ))
+            .unwrap();
+        executer.run_to_end().unwrap();
+
+        // the second ADDD waits until the adder finishes the first one
+        let (first, second) = (&executer.insts_comp[0], &executer.insts_comp[1]);
+        assert_eq!(first.start_cycle, Some(2));
+        assert_eq!(first.exec_cycle, Some(3));
+        assert_eq!(second.start_cycle, Some(4));
+        assert_eq!(second.exec_cycle, Some(5));
+        assert_eq!(executer.stats.unit_waits[&EuType::Adder], 2);
+    }
+
+    #[test]
+    fn test_pipelined_units() {
+        let config = MachineConfig::default()
+            .with_issue_width(4)
+            .with_rs_count(RsType::MULT, 4)
+            .with_eu_count(EuType::Multiplier, 1)
+            .with_eu_count(EuType::Divider, 1)
+            .with_eu_interval(EuType::Multiplier, 1);
+        let mut executer = Executer::new(config).unwrap();
+        executer
+            .add_insts(&parse(
+                r"
+            MULTD F2 F0 F0
+            MULTD F4 F0 F0
+            DIVD F6 F0 F10
+            DIVD F8 F0 F10",
+            ))
+            .unwrap();
+        executer.run_to_end().unwrap();
+
+        let timing = |seq: usize| {
+            let inst = executer.insts_comp.iter().find(|i| i.seq == seq).unwrap();
+            (inst.start_cycle.unwrap(), inst.exec_cycle.unwrap())
+        };
+        // the multiplier accepts a MULTD every cycle
+        assert_eq!(timing(0), (2, 11));
+        assert_eq!(timing(1), (3, 12));
+        // the divider is busy until the first DIVD finishes
+        assert_eq!(timing(2), (2, 21));
+        assert_eq!(timing(3), (22, 41));
+    }
+
+    #[test]
+    fn test_issue_width() {
+        let mut executer = Executer::new(MachineConfig::default().with_issue_width(2)).unwrap();
+        executer.add_insts(&parse(PROGRAM)).unwrap();
+        let issued = |report: &CycleReport| {
+            report
+                .events
+                .iter()
+                .filter(|e| matches!(e, Event::Issue { .. }))
+                .count()
+        };
+
+        assert_eq!(issued(&executer.step().unwrap()), 2);
+        // DIVD and the MULTD depending on it are issued together
+        let report = executer.step().unwrap();
+        assert_eq!(issued(&report), 2);
+        let divd = report
+            .rs
+            .iter()
+            .find(|rs| rs.inst().is_some_and(|i| i.op == Type::DIVD));
+        let multd = report
+            .rs
+            .iter()
+            .find(|rs| rs.inst().is_some_and(|i| i.op == Type::MULTD));
+        assert_eq!(multd.unwrap().qj, Some(divd.unwrap().tag()));
+        assert_eq!(issued(&executer.step().unwrap()), 2);
+        // the last MULTD waits for a free multiplier
+        assert_eq!(issued(&executer.step().unwrap()), 0);
+        assert_eq!(executer.stats.stalls[&Stall::NoFreeRs(RsType::MULT)], 1);
+
+        executer.run_to_end().unwrap();
+        let mut single = Executer::new(MachineConfig::default()).unwrap();
+        single.add_insts(&parse(PROGRAM)).unwrap();
+        single.run_to_end().unwrap();
+        assert!(executer.cycle < single.cycle);
+    }
+
+    #[test]
+    fn test_step_report() {
+        let mut executer = Executer::new(MachineConfig::default()).unwrap();
+        executer.add_insts(&parse(PROGRAM)).unwrap();
+
+        let report = executer.step().unwrap();
+        assert_eq!(report.cycle, 1);
+        assert!(!report.finished);
+        let busy = report.rs.iter().find(|rs| rs.inst().is_some()).unwrap();
+        assert_eq!(
+            report.events,
+            vec![Event::Issue {
+                seq: 0,
+                rs: busy.id
+            }]
+        );
+        assert_eq!(busy.inst().unwrap().op, Type::LD);
+
+        let report = executer.step().unwrap();
+        assert!(report.events.contains(&Event::ExecStart { seq: 0 }));
+        assert_eq!(report.insts.len(), 2);
+
+        let mut reports = vec![report];
+        while !executer.finished {
+            reports.push(executer.step().unwrap());
+        }
+        let last = reports.last().unwrap();
+        assert_eq!(last.insts.len(), 8);
+        assert!(last.insts.iter().all(|inst| last.is_completed(inst)));
+
+        // every instruction writes its result exactly once
+        let writes = reports
+            .iter()
+            .flat_map(|r| r.events.iter())
+            .filter(|e| matches!(e, Event::Write { .. }))
+            .count();
+        assert_eq!(writes, 8);
+    }
+
+    #[test]
+    fn test_advance() {
+        let mut stepped = Executer::new(MachineConfig::default()).unwrap();
+        stepped.add_insts(&parse(PROGRAM)).unwrap();
+        let mut last = stepped.report();
+        while !stepped.finished {
+            last = stepped.step().unwrap();
+        }
+
+        // only the last cycle is reported
+        let mut executer = Executer::new(MachineConfig::default()).unwrap();
+        executer.add_insts(&parse(PROGRAM)).unwrap();
+        let mut reports = Vec::new();
+        while !executer.finished {
+            reports.extend(executer.advance().unwrap());
+        }
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert!(report.finished);
+        assert_eq!(report.cycle, last.cycle);
+        let timing = |r: &CycleReport| {
+            r.insts
+                .iter()
+                .map(|i| (i.emit_cycle, i.start_cycle, i.exec_cycle, i.write_cycle))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(timing(report), timing(&last));
+    }
+
+    #[test]
+    fn test_bad_registers() {
+        let config = MachineConfig::default().with_fp_regs(4);
+        let mut executer = Executer::new(config.clone()).unwrap();
+        let program = parse_program("ADDD F0 F2 F4\nADDD F6 F10 F2").unwrap();
+        let reg = Unit::Fu(FuId::new(10));
+        assert_eq!(
+            executer.load(&program),
+            Err(SimError::BadRegister { pc: Some(1), reg })
+        );
+        // nothing was loaded
+        assert!(executer.program.is_empty());
+
+        let program = parse_program(".reg F10, 1.0\nADDD F0 F2 F4").unwrap();
+        let mut executer = Executer::new(config.clone()).unwrap();
+        assert_eq!(
+            executer.load(&program),
+            Err(SimError::BadRegister { pc: None, reg })
+        );
+
+        let config = config.with_int_regs(4);
+        let program = parse_program("DADDUI R2 R6 #8").unwrap();
+        let mut executer = Executer::new(config.clone()).unwrap();
+        let reg = Unit::Regs(RegId::new(6));
+        assert_eq!(
+            executer.load(&program),
+            Err(SimError::BadRegister { pc: Some(0), reg })
+        );
+        let program = parse_program(".reg R4, 1").unwrap();
+        let mut executer = Executer::new(config).unwrap();
+        let reg = Unit::Regs(RegId::new(4));
+        assert_eq!(
+            executer.load(&program),
+            Err(SimError::BadRegister { pc: None, reg })
+        );
+    }
+
+    #[test]
+    fn test_undecided_branch() {
+        // the loaded value is only an expression in symbolic mode
+        let program = "LD F2 0 R1\nBNEZ F2 Done\nADDD F0 F2 F4\nDone: ADDD F6 F2 F4";
+        let mut executer = Executer::new(MachineConfig::default()).unwrap();
+        executer.add_insts(&parse(program)).unwrap();
+        let err = executer.run_to_end().unwrap_err();
+        let SimError::UndecidedBranch { pc, condition } = err else {
+            panic!("expected an undecided branch, got {err:?}");
+        };
+        assert_eq!(pc, 1);
+        assert!(condition.contains("M["));
+
+        let config = MachineConfig::default().with_eval_mode(EvalMode::Numeric);
+        let mut executer = Executer::new(config).unwrap();
+        executer.add_insts(&parse(program)).unwrap();
+        executer.run_to_end().unwrap();
+        assert_eq!(executer.insts_comp.len(), 4);
+    }
+
+    #[test]
+    fn test_undefined_label() {
+        let mut executer = Executer::new(MachineConfig::default()).unwrap();
+        let label = String::from("Done");
+        assert_eq!(
+            executer.add_insts(&parse("ADDD F0 F2 F4\nBNEZ R1 Done")),
+            Err(SimError::UndefinedLabel { pc: 1, label })
+        );
+        assert!(executer.program.is_empty());
+
+        // a label may be defined by earlier instructions
+        executer.add_insts(&parse("Loop: ADDD F0 F2 F4")).unwrap();
+        executer.add_insts(&parse("BNEZ R1 Loop")).unwrap();
+        assert_eq!(executer.program.len(), 2);
+    }
+
+    #[test]
+    fn test_symbolic_alias() {
+        let insts = parse("MULTD F6 F0 F2\nSD F6 0 R3\nLD F2 0 R1\nLD F4 8 R3");
+        let mut executer = Executer::new(MachineConfig::default()).unwrap();
+        executer.add_insts(&insts).unwrap();
+        executer.run_to_end().unwrap();
+        let inst = |pc: usize| {
+            executer
+                .insts_comp
+                .iter()
+                .find(|inst| inst.pc == pc)
+                .unwrap()
+        };
+        // R1 and R3 may be the same register value
+        assert!(inst(2).start_cycle > inst(1).write_cycle);
+        // but 8(R3) is not 0(R3)
+        assert!(inst(3).write_cycle < inst(1).start_cycle);
     }
 }
@@ -5,7 +5,7 @@ use console::style;
 use super::*;
 
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub enum Type {
     ADDD,
     SUBD,
@@ -13,122 +13,171 @@ pub enum Type {
     DIVD,
     LD,
     SD,
+    BEQ,
+    BNE,
+    BEQZ,
+    BNEZ,
+    ADD,
+    SUB,
+    ADDI,
+    SUBI,
+    DADDI,
+    DADDUI,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Instruction {
     pub op: Type,
-    pub dest: Unit,
+    pub dest: Option<Unit>,
     pub src1: Option<Value>,
     pub src2: Option<Value>,
 
+    /// The label of the instruction.
+    pub label: Option<String>,
+    /// The label a branch jumps to.
+    pub target: Option<String>,
+    /// The index of the instruction in the program.
+    pub pc: usize,
+    /// The order in which the instruction was issued.
+    pub seq: usize,
+    /// The predicted and actual direction of a branch.
+    pub predicted: Option<bool>,
+    pub taken: Option<bool>,
+    /// The exception raised when the instruction completed.
+    pub exception: Option<Exception>,
+
     pub emit_cycle: Option<u64>,
     pub start_cycle: Option<u64>,
     pub exec_cycle: Option<u64>,
     pub write_cycle: Option<u64>,
+    pub commit_cycle: Option<u64>,
+    /// The cycle the instruction was squashed in, if it was.
+    pub squash_cycle: Option<u64>,
 
     // The number of cycles left to finish the instruction.
     left_cycle: Option<u64>,
+    // The number of cycles to execute the instruction.
+    latency: u64,
 }
 
 impl Instruction {
     pub fn new(op: Type, dest: Unit) -> Instruction {
+        Instruction::with_dest(op, Some(dest))
+    }
+
+    /// Create a branch instruction jumping to `target`.
+    pub fn branch(op: Type, target: String) -> Instruction {
+        let mut inst = Instruction::with_dest(op, None);
+        inst.target.replace(target);
+        inst
+    }
+
+    fn with_dest(op: Type, dest: Option<Unit>) -> Instruction {
         Instruction {
             op,
             dest,
             src1: None,
             src2: None,
+            label: None,
+            target: None,
+            pc: 0,
+            seq: 0,
+            predicted: None,
+            taken: None,
+            exception: None,
             emit_cycle: None,
             start_cycle: None,
             exec_cycle: None,
             write_cycle: None,
+            commit_cycle: None,
+            squash_cycle: None,
             left_cycle: None,
+            latency: 0,
         }
     }
 
-    fn latency(&self) -> u64 {
-        match self.op {
-            Type::ADDD | Type::SUBD | Type::LD | Type::SD => 2,
-            Type::MULTD => 10,
-            Type::DIVD => 20,
-        }
-    }
-
-    /// Emit the instruction.
-    pub fn emit(&mut self, cycle: u64) {
+    /// Emit the instruction, which takes `latency` cycles to execute.
+    pub fn emit(&mut self, cycle: u64, latency: u64) {
         self.emit_cycle.replace(cycle);
-        self.left_cycle.replace(self.latency());
+        self.latency = latency;
+        self.left_cycle.replace(latency);
     }
 
     /// Execute the instruction.
+    ///
+    /// Returns true in the cycle after the last execution cycle,
+    /// when the result can be written.
     pub fn exec(&mut self, cycle: u64) -> bool {
-        if let Some(left) = self.left_cycle {
-            if left == 0 {
+        match self.left_cycle {
+            Some(0) => {
                 self.left_cycle.take();
-                self.exec_cycle.replace(cycle - 1);
                 true
-            } else if left == self.latency() {
-                self.start_cycle.replace(cycle);
-                self.left_cycle.replace(left - 1);
-                false
-            } else {
+            }
+            Some(left) => {
+                if left == self.latency {
+                    self.start_cycle.replace(cycle);
+                }
+                if left == 1 {
+                    self.exec_cycle.replace(cycle);
+                }
                 self.left_cycle.replace(left - 1);
                 false
             }
-        } else {
-            false
+            None => false,
         }
     }
 
+    /// Make an instruction which has started executing take `cycles`
+    /// more cycles, e.g. a load missing in the cache.
+    pub fn delay(&mut self, cycles: u64) {
+        if let (Some(left), true) = (self.left_cycle, cycles > 0) {
+            self.left_cycle.replace(left + cycles);
+            self.latency += cycles;
+            self.exec_cycle.take();
+        }
+    }
+
+    /// The number of cycles the instruction takes to execute.
+    #[inline]
+    pub fn latency(&self) -> u64 {
+        self.latency
+    }
+
     #[inline]
     pub fn write(&mut self, cycle: u64) {
         self.write_cycle.replace(cycle);
     }
+
+    #[inline]
+    pub fn commit(&mut self, cycle: u64) {
+        self.commit_cycle.replace(cycle);
+    }
+
+    /// The assembly text of the instruction.
+    pub fn text(&self) -> String {
+        let mut text = format!("{:?}", self.op);
+        if let Some(dest) = self.dest {
+            text += &format!(" {dest}");
+        }
+        if let Some(src1) = self.src1.as_ref() {
+            text += &format!(" {src1}");
+        }
+        if let (Some(src2), false) = (self.src2.as_ref(), self.op.is_branch_zero()) {
+            text += &format!(" {src2}");
+        }
+        if let Some(target) = self.target.as_ref() {
+            text += &format!(" {target}");
+        }
+        text
+    }
 }
 
 impl FromStr for Instruction {
-    type Err = ();
+    type Err = ParseError;
 
+    /// Parse a single instruction, reporting errors on line 1.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut iter = s.split_whitespace();
-        let op = iter.next().expect("no op").parse()?;
-
-        let dest = iter.next().expect("no dest").parse()?;
-
-        let mut src1 = None;
-        let mut src2 = None;
-
-        let next = iter.next().expect("no src1");
-        src1.replace(value::new(match next.parse::<Unit>() {
-            Ok(u) => u.into(),
-            Err(_) => next
-                .trim_end_matches('+')
-                .parse::<i64>()
-                .map_err(|_| ())?
-                .into(),
-        }));
-
-        let next = iter.next().expect("no src2");
-        src2.replace(value::new(match next.parse::<Unit>() {
-            Ok(u) => u.into(),
-            Err(_) => next
-                .trim_end_matches('+')
-                .parse::<i64>()
-                .map_err(|_| ())?
-                .into(),
-        }));
-
-        Ok(Instruction {
-            op,
-            dest,
-            src1,
-            src2,
-            emit_cycle: None,
-            exec_cycle: None,
-            start_cycle: None,
-            write_cycle: None,
-            left_cycle: None,
-        })
+        parser::parse_line(1, s)
     }
 }
 
@@ -143,21 +192,73 @@ impl FromStr for Type {
             "DIVD" => Ok(Type::DIVD),
             "LD" => Ok(Type::LD),
             "SD" => Ok(Type::SD),
+            "BEQ" => Ok(Type::BEQ),
+            "BNE" => Ok(Type::BNE),
+            "BEQZ" => Ok(Type::BEQZ),
+            "BNEZ" => Ok(Type::BNEZ),
+            "ADD" => Ok(Type::ADD),
+            "SUB" => Ok(Type::SUB),
+            "ADDI" => Ok(Type::ADDI),
+            "SUBI" => Ok(Type::SUBI),
+            "DADDI" => Ok(Type::DADDI),
+            "DADDUI" => Ok(Type::DADDUI),
             _ => Err(()),
         }
     }
 }
 
 impl Type {
+    /// Every instruction type.
+    pub const ALL: [Type; 16] = [
+        Type::ADDD,
+        Type::SUBD,
+        Type::MULTD,
+        Type::DIVD,
+        Type::LD,
+        Type::SD,
+        Type::BEQ,
+        Type::BNE,
+        Type::BEQZ,
+        Type::BNEZ,
+        Type::ADD,
+        Type::SUB,
+        Type::ADDI,
+        Type::SUBI,
+        Type::DADDI,
+        Type::DADDUI,
+    ];
+
     pub fn op_str(&self) -> &'static str {
         match self {
-            Type::ADDD => "+",
-            Type::SUBD => "-",
+            Type::ADDD | Type::ADD | Type::ADDI | Type::DADDI | Type::DADDUI => "+",
+            Type::SUBD | Type::SUB | Type::SUBI => "-",
             Type::MULTD => "*",
             Type::DIVD => "/",
+            Type::BEQ | Type::BEQZ => "==",
+            Type::BNE | Type::BNEZ => "!=",
             _ => "",
         }
     }
+
+    #[inline]
+    pub fn is_branch(&self) -> bool {
+        matches!(self, Type::BEQ | Type::BNE | Type::BEQZ | Type::BNEZ)
+    }
+
+    /// Whether the instruction is executed by the integer ALU.
+    #[inline]
+    pub fn is_int(&self) -> bool {
+        matches!(
+            self,
+            Type::ADD | Type::SUB | Type::ADDI | Type::SUBI | Type::DADDI | Type::DADDUI
+        )
+    }
+
+    /// Whether the instruction is a branch comparing with zero.
+    #[inline]
+    pub fn is_branch_zero(&self) -> bool {
+        matches!(self, Type::BEQZ | Type::BNEZ)
+    }
 }
 
 impl std::fmt::Display for Type {
@@ -169,20 +270,19 @@ impl std::fmt::Display for Type {
             Type::DIVD => style(format!("{self:?}")).blue(),
             Type::LD => style(format!("{self:?}")).cyan(),
             Type::SD => style(format!("{self:?}")).magenta(),
+            Type::BEQ | Type::BNE | Type::BEQZ | Type::BNEZ => style(format!("{self:?}")).white(),
+            _ => style(format!("{self:?}")).green().dim(),
         };
         write!(f, "{s:<5}")
     }
 }
 
+/// The timing of the instruction: issue, exec start, exec complete and
+/// write result. The alternate form `{:#}` adds the commit, for
+/// machines with a reorder buffer.
 impl std::fmt::Display for Instruction {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let inst = format!(
-            "{:?} {} {} {}",
-            self.op,
-            self.dest,
-            self.src1.as_ref().unwrap(),
-            self.src2.as_ref().unwrap()
-        );
+        let inst = self.text();
         let emit = match self.emit_cycle {
             Some(c) => c.to_string(),
             None => " ".to_string(),
@@ -208,6 +308,13 @@ impl std::fmt::Display for Instruction {
             style(exec).yellow(),
             style(write).green()
         )?;
+        if f.alternate() {
+            let commit = match self.commit_cycle {
+                Some(c) => c.to_string(),
+                None => " ".to_string(),
+            };
+            write!(f, ", {:>3}", style(commit).magenta())?;
+        }
         Ok(())
     }
 }
@@ -216,6 +323,20 @@ impl std::fmt::Display for Instruction {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_display() {
+        let mut inst = "ADDD F0 F2 F4".parse::<Instruction>().unwrap();
+        inst.emit_cycle = Some(1);
+        inst.start_cycle = Some(2);
+        inst.exec_cycle = Some(3);
+        inst.write_cycle = Some(4);
+        inst.commit_cycle = Some(5);
+        let plain = console::strip_ansi_codes(&inst.to_string()).into_owned();
+        assert!(plain.ends_with(":   1,   2,   3,   4"));
+        let rob = console::strip_ansi_codes(&format!("{inst:#}")).into_owned();
+        assert!(rob.ends_with(":   1,   2,   3,   4,   5"));
+    }
+
     #[test]
     fn test_parse() {
         let insts = [
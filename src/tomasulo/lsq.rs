@@ -0,0 +1,249 @@
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use super::*;
+
+/// When a memory access may start before an earlier one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LsqPolicy {
+    /// Loads wait for every earlier store, and without a reorder
+    /// buffer stores wait for every earlier access.
+    Conservative,
+    /// Accesses only wait for earlier ones to the same address, or
+    /// whose address is not known yet.
+    #[default]
+    Address,
+}
+
+/// How an access depends on the earlier ones.
+enum Dependence {
+    None,
+    /// It waits for an earlier access.
+    Wait,
+    /// It is a load taking the value of an earlier store.
+    Forward(Value),
+}
+
+#[derive(Debug, Clone)]
+pub struct LsqEntry {
+    pub seq: usize,
+    pub rs: RsId,
+    pub op: Type,
+    /// The effective address, once the base register is known.
+    pub addr: Option<Value>,
+    /// The value of a store, once it is known.
+    pub value: Option<Value>,
+}
+
+/// The load/store queue.
+///
+/// Loads and stores enter the queue in program order when they are
+/// issued. A load leaves it when it writes its result and a store
+/// when it writes the memory: at write without a reorder buffer,
+/// otherwise at commit. Loads read the memory when they finish
+/// executing, so a load only starts once no earlier store it may
+/// depend on is left in the queue.
+///
+/// With forwarding, a load whose address matches the youngest earlier
+/// store it may depend on takes the value of the store as soon as it
+/// is known, instead of waiting for the memory to be written.
+#[derive(Debug, Clone)]
+pub struct LoadStoreQueue {
+    entries: VecDeque<LsqEntry>,
+    policy: LsqPolicy,
+    mode: EvalMode,
+    forwarding: bool,
+    /// Stores write the memory in order at commit.
+    in_order: bool,
+}
+
+impl LoadStoreQueue {
+    pub fn new(config: &MachineConfig) -> LoadStoreQueue {
+        LoadStoreQueue {
+            entries: VecDeque::new(),
+            policy: config.lsq_policy(),
+            mode: config.eval_mode(),
+            forwarding: config.forwarding(),
+            in_order: config.rob_size().is_some(),
+        }
+    }
+
+    /// Add an issued load or store.
+    pub fn push(&mut self, seq: usize, rs: RsId, op: Type) {
+        self.entries.push_back(LsqEntry {
+            seq,
+            rs,
+            op,
+            addr: None,
+            value: None,
+        });
+    }
+
+    /// Remove an access which has completed.
+    pub fn remove(&mut self, seq: usize) {
+        self.entries.retain(|entry| entry.seq != seq);
+    }
+
+    /// Iterate over the accesses in program order.
+    pub fn iter(&self) -> impl Iterator<Item = &LsqEntry> {
+        self.entries.iter()
+    }
+
+    /// Compute the addresses whose base register has become known,
+    /// and keep the values of the stores once they are known.
+    pub fn update(&mut self, rs: &ReservationStation) {
+        for entry in self.entries.iter_mut() {
+            let Some(inner) = rs
+                .iter()
+                .find(|inner| inner.inst().is_some_and(|inst| inst.seq == entry.seq))
+            else {
+                continue;
+            };
+            if entry.op == Type::SD && entry.value.is_none() {
+                entry.value = inner.vk.clone();
+            }
+            if entry.addr.is_some() {
+                continue;
+            }
+            let base = match entry.op {
+                Type::LD => inner.vk.as_ref(),
+                _ => inner.vj.as_ref(),
+            };
+            if let (Some(offset), Some(base)) = (inner.addr.as_ref(), base) {
+                let addr = value::apply_op(Type::ADD, offset.clone(), base.clone(), self.mode);
+                entry.addr.replace(addr);
+            }
+        }
+    }
+
+    /// The stations which may not start because of an earlier access.
+    pub fn blocked(&self) -> Vec<RsId> {
+        (0..self.entries.len())
+            .filter(|&i| matches!(self.dependence(i), Dependence::Wait))
+            .map(|i| self.entries[i].rs)
+            .collect()
+    }
+
+    /// The loads which take their value from an earlier store, with
+    /// the value. Always empty unless forwarding is enabled.
+    pub fn forwards(&self) -> Vec<(RsId, Value)> {
+        (0..self.entries.len())
+            .filter_map(|i| match self.dependence(i) {
+                Dependence::Forward(value) => Some((self.entries[i].rs, value)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// How the `i`-th access depends on the earlier ones.
+    fn dependence(&self, i: usize) -> Dependence {
+        let entry = &self.entries[i];
+        // with a reorder buffer stores only write the memory at commit
+        if entry.op == Type::SD && self.in_order {
+            return Dependence::None;
+        }
+        let mut earlier = self
+            .entries
+            .iter()
+            .take(i)
+            .rev()
+            .filter(|earlier| entry.op == Type::SD || earlier.op == Type::SD)
+            .filter(|earlier| match self.policy {
+                LsqPolicy::Conservative => true,
+                LsqPolicy::Address => !self.disjoint(earlier, entry),
+            });
+        match earlier.next() {
+            None => Dependence::None,
+            // the youngest earlier store to the same address has the value
+            Some(store) if self.forwarding && entry.op == Type::LD && self.same(store, entry) => {
+                match store.value.clone() {
+                    Some(value) => Dependence::Forward(value),
+                    None => Dependence::Wait,
+                }
+            }
+            Some(_) => Dependence::Wait,
+        }
+    }
+
+    /// Whether two accesses are known to use the same address.
+    fn same(&self, a: &LsqEntry, b: &LsqEntry) -> bool {
+        match (a.addr.as_ref(), b.addr.as_ref()) {
+            (Some(a), Some(b)) => match (split(a), split(b)) {
+                (Some((base_a, a)), Some((base_b, b))) => base_a == base_b && a == b,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether two accesses are known to use different addresses.
+    ///
+    /// Symbolic addresses are only known to differ when they add
+    /// different offsets to the same base, otherwise they may alias.
+    fn disjoint(&self, a: &LsqEntry, b: &LsqEntry) -> bool {
+        match (a.addr.as_ref(), b.addr.as_ref()) {
+            (Some(a), Some(b)) => match (split(a), split(b)) {
+                (Some((base_a, a)), Some((base_b, b))) => base_a == base_b && a != b,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Split an address into a base expression, `None` for a number, and
+/// a constant offset.
+///
+/// Returns `None` if the base depends on the memory, which may be
+/// written between two accesses, so the same expression may not be
+/// the same address.
+fn split(addr: &Value) -> Option<(Option<&Value>, i64)> {
+    if let Some(addr) = addr.eval() {
+        return Some((None, addr as i64));
+    }
+    match &**addr {
+        ValueInner::Op(Type::ADD | Type::ADDI | Type::DADDI | Type::DADDUI, a, b) => {
+            match (a.eval(), b.eval()) {
+                (Some(offset), _) => split(b).map(|(base, n)| (base, n + offset as i64)),
+                (_, Some(offset)) => split(a).map(|(base, n)| (base, n + offset as i64)),
+                _ => whole(addr),
+            }
+        }
+        ValueInner::Op(Type::SUB | Type::SUBI, a, b) => match b.eval() {
+            Some(offset) => split(a).map(|(base, n)| (base, n - offset as i64)),
+            None => whole(addr),
+        },
+        _ => whole(addr),
+    }
+}
+
+/// An address which is its own base, unless it depends on the memory.
+fn whole(addr: &Value) -> Option<(Option<&Value>, i64)> {
+    fn reads_memory(value: &ValueInner) -> bool {
+        match value {
+            ValueInner::MemAddr(_) => true,
+            ValueInner::Op(_, a, b) => reads_memory(a) || reads_memory(b),
+            _ => false,
+        }
+    }
+    match reads_memory(addr) {
+        true => None,
+        false => Some((Some(addr), 0)),
+    }
+}
+
+impl FromStr for LsqPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "conservative" => Ok(LsqPolicy::Conservative),
+            "address" => Ok(LsqPolicy::Address),
+            _ => Err(()),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::BTreeMap;
+
+use console::style;
+
+/// The data memory image.
+///
+/// Memory is addressed in bytes and holds a double at each
+/// address written. Addresses never written read as zero.
+#[derive(Clone, Default, PartialEq)]
+pub struct Memory {
+    inner: BTreeMap<i64, f64>,
+}
+
+impl Memory {
+    pub fn new() -> Memory {
+        Memory::default()
+    }
+
+    #[inline]
+    pub fn read(&self, addr: i64) -> f64 {
+        self.inner.get(&addr).copied().unwrap_or(0.0)
+    }
+
+    #[inline]
+    pub fn write(&mut self, addr: i64, value: f64) {
+        self.inner.insert(addr, value);
+    }
+
+    /// Iterate over the written addresses in order.
+    pub fn iter(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        self.inner.iter().map(|(addr, value)| (*addr, *value))
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+}
+
+impl std::fmt::Debug for Memory {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (addr, value) in self.iter() {
+            writeln!(
+                f,
+                "{} : {}",
+                style(format!("M[{addr}]")).magenta().underlined(),
+                style(format!("{value:2.2}")).cyan().underlined()
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,31 @@
+pub mod cache;
+pub mod cdb;
+pub mod config;
+pub mod exception;
 pub mod executer;
 pub mod inst;
+pub mod lsq;
+pub mod memory;
+pub mod parser;
+pub mod predictor;
+pub mod program;
+pub mod render;
+pub mod report;
+pub mod stats;
 pub mod units;
 pub mod value;
 
+pub use cache::{Cache, CacheAccess, CacheConfig, Mshr};
+pub use cdb::{Cdb, CdbPolicy};
+pub use config::*;
+pub use exception::{Exception, Fault};
 pub use inst::*;
+pub use lsq::{LoadStoreQueue, LsqPolicy};
+pub use memory::Memory;
+pub use parser::ParseError;
+pub use predictor::{Predictor, PredictorKind};
+pub use program::Program;
+pub use report::{CycleReport, Event};
+pub use stats::{Stall, Stats};
 pub use units::*;
-pub use value::{Value, ValueInner};
+pub use value::{EvalMode, Value, ValueInner};
@@ -0,0 +1,897 @@
+use std::collections::HashMap;
+
+use super::*;
+
+/// A range of a source line, with the line and column counted from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub len: usize,
+}
+
+/// An error in the source of a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The opcode is not a known instruction.
+    UnknownOpcode { op: String, span: Span },
+    /// The line ends before an operand of the instruction.
+    MissingOperand { operand: &'static str, span: Span },
+    /// The operand is not a valid register.
+    BadRegister { reg: String, span: Span },
+    /// A double precision register with an odd number.
+    OddFpRegister { reg: String, span: Span },
+    /// A register past the end of its register file.
+    RegisterOutOfRange {
+        reg: String,
+        count: usize,
+        span: Span,
+    },
+    /// An integer register where the instruction expects a floating
+    /// point one, or the other way round.
+    WrongRegisterClass {
+        reg: String,
+        expected: &'static str,
+        span: Span,
+    },
+    /// An offset or immediate value which is not a number.
+    BadOffset { offset: String, span: Span },
+    /// An operand after the last operand of the instruction.
+    UnexpectedOperand { operand: String, span: Span },
+    /// A branch to a label which is not defined.
+    UndefinedLabel { label: String, span: Span },
+    /// A label defined more than once.
+    DuplicateLabel { label: String, span: Span },
+    /// The directive is not a known one.
+    UnknownDirective { directive: String, span: Span },
+    /// The value of a directive is not a number of the right kind.
+    BadValue { value: String, span: Span },
+    /// An instruction in the `.data` section.
+    InstructionInData { span: Span },
+}
+
+impl ParseError {
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnknownOpcode { span, .. }
+            | ParseError::MissingOperand { span, .. }
+            | ParseError::BadRegister { span, .. }
+            | ParseError::OddFpRegister { span, .. }
+            | ParseError::RegisterOutOfRange { span, .. }
+            | ParseError::WrongRegisterClass { span, .. }
+            | ParseError::BadOffset { span, .. }
+            | ParseError::UnexpectedOperand { span, .. }
+            | ParseError::UndefinedLabel { span, .. }
+            | ParseError::DuplicateLabel { span, .. }
+            | ParseError::UnknownDirective { span, .. }
+            | ParseError::BadValue { span, .. }
+            | ParseError::InstructionInData { span } => *span,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            ParseError::UnknownOpcode { op, .. } => format!("unknown opcode `{op}`"),
+            ParseError::MissingOperand { operand, .. } => format!("missing {operand}"),
+            ParseError::BadRegister { reg, .. } => format!("invalid register `{reg}`"),
+            ParseError::OddFpRegister { reg, .. } => {
+                format!("floating point register `{reg}` must be even")
+            }
+            ParseError::RegisterOutOfRange { reg, count, .. } => {
+                let kind = match reg.starts_with(['F', 'f']) {
+                    true => "floating point",
+                    false => "integer",
+                };
+                format!("register `{reg}` does not exist, the machine has {count} {kind} registers")
+            }
+            ParseError::WrongRegisterClass { reg, expected, .. } => {
+                format!("expected {expected} register, found `{reg}`")
+            }
+            ParseError::BadOffset { offset, .. } => format!("invalid offset `{offset}`"),
+            ParseError::UnexpectedOperand { operand, .. } => {
+                format!("unexpected operand `{operand}`")
+            }
+            ParseError::UndefinedLabel { label, .. } => format!("undefined label `{label}`"),
+            ParseError::DuplicateLabel { label, .. } => {
+                format!("label `{label}` is defined more than once")
+            }
+            ParseError::UnknownDirective { directive, .. } => {
+                format!("unknown directive `{directive}`")
+            }
+            ParseError::BadValue { value, .. } => format!("invalid value `{value}`"),
+            ParseError::InstructionInData { .. } => {
+                String::from("instruction in the `.data` section")
+            }
+        }
+    }
+
+    /// Describe the error with the source line it occurs in,
+    /// marking the span with carets:
+    ///
+    /// ```text
+    /// error: unknown opcode `MUL`
+    ///  --> 3:5
+    ///   |
+    /// 3 |     MUL F0 F2 F4
+    ///   |     ^^^
+    /// ```
+    pub fn snippet(&self, source: &str) -> String {
+        let span = self.span();
+        let line = source.lines().nth(span.line - 1).unwrap_or("");
+        let number = span.line.to_string();
+        let pad = " ".repeat(number.len());
+        format!(
+            "error: {}\n{pad}--> {}:{}\n{pad} |\n{number} | {line}\n{pad} | {}{}\n",
+            self.message(),
+            span.line,
+            span.column,
+            " ".repeat(span.column - 1),
+            "^".repeat(span.len.max(1)),
+        )
+    }
+}
+
+/// Assemble a program.
+///
+/// Instructions are written one per line, with operands separated by
+/// spaces or commas, and may be preceded by labels. A label may also
+/// stand on its own line, naming the next instruction. Comments start
+/// with `;`, or with `#` when it is not the prefix of a number.
+///
+/// ```text
+/// .data 100
+/// A:    .double 1.5, 2.5    ; memory at 100 and 108
+/// N:    .word 2             ; memory at 116
+///
+/// .text
+/// .reg  R1, 16              ; initial register values
+/// .reg  F2, 0.5
+/// Loop:
+///       LD     F0, A-8(R1)
+///       ADDD   F4, F0, F2
+///       SD     F4, A-8(R1)
+///       DADDUI R1, R1, #-8
+///       BNEZ   R1, Loop
+/// ```
+///
+/// `.double` and `.word` write consecutive values to memory, from the
+/// address given to `.data` (0 by default). The memory holds a whole
+/// value at an address, so each value takes 8 bytes, the step of the
+/// load and store offsets over an array. A label in front of them names their address, which can be
+/// used as an offset or an immediate value. `.reg` sets the initial
+/// value of a register.
+///
+/// Every line is parsed even after an error, so that all the
+/// errors of the program are reported at once.
+///
+/// Registers are only checked against the registers an instruction
+/// can name. Use [`parse_program_with`] to check them against the
+/// register files of a machine.
+pub fn parse_program(source: &str) -> Result<Program, Vec<ParseError>> {
+    assemble(source, RegFile::ALL)
+}
+
+/// Assemble a program for a machine, rejecting the registers
+/// past the end of its register files.
+pub fn parse_program_with(
+    source: &str,
+    config: &MachineConfig,
+) -> Result<Program, Vec<ParseError>> {
+    let regs = RegFile {
+        fp: config.fp_regs(),
+        int: config.int_regs(),
+    };
+    assemble(source, regs)
+}
+
+fn assemble(source: &str, regs: RegFile) -> Result<Program, Vec<ParseError>> {
+    let mut asm = Assembler {
+        regs,
+        ..Default::default()
+    };
+    for (i, line) in source.lines().enumerate() {
+        if let Err(e) = asm.line(Tokens::new(i + 1, line)) {
+            asm.errors.push(e);
+        }
+    }
+    asm.assemble()
+}
+
+/// A branch target and where it is written.
+type Target = (String, Span);
+
+/// A label and where it is defined.
+type Label<'a> = (Span, &'a str);
+
+/// The number of registers of each register file.
+#[derive(Debug, Clone, Copy)]
+struct RegFile {
+    fp: usize,
+    int: usize,
+}
+
+impl RegFile {
+    /// Every register an instruction can name.
+    const ALL: RegFile = RegFile { fp: 128, int: 256 };
+}
+
+impl Default for RegFile {
+    fn default() -> Self {
+        RegFile::ALL
+    }
+}
+
+/// The register file an operand must be in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RegClass {
+    Fp,
+    Int,
+}
+
+impl RegClass {
+    /// The register class of the destination and of the source
+    /// operands of an instruction, if it expects one.
+    fn of(op: Type) -> Option<RegClass> {
+        match op {
+            Type::ADDD | Type::SUBD | Type::MULTD | Type::DIVD | Type::LD | Type::SD => {
+                Some(RegClass::Fp)
+            }
+            _ if op.is_int() => Some(RegClass::Int),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            RegClass::Fp => "a floating point",
+            RegClass::Int => "an integer",
+        }
+    }
+}
+
+/// Parse a line holding a single instruction, which may be labeled.
+pub(crate) fn parse_line(line_no: usize, line: &str) -> Result<Instruction, ParseError> {
+    let mut tokens = Tokens::new(line_no, line);
+    let labels = tokens.labels();
+    let (mut inst, _) = parse_inst(&mut tokens, &HashMap::new(), RegFile::ALL)?;
+    inst.label = labels.last().map(|(_, label)| label.to_string());
+    Ok(inst)
+}
+
+/// The section the assembler is writing to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Section {
+    #[default]
+    Text,
+    Data,
+}
+
+/// The state of the assembler.
+///
+/// The data is laid out while reading the lines, and the instructions
+/// are parsed once every data label is known.
+#[derive(Default)]
+struct Assembler<'a> {
+    program: Program,
+    regs: RegFile,
+    errors: Vec<ParseError>,
+    section: Section,
+    /// The address of the next data value.
+    addr: i64,
+    /// The addresses of the data labels.
+    symbols: HashMap<String, i64>,
+    /// The labels of the text and the instructions they name, if any.
+    text: Vec<(Vec<Label<'a>>, Option<Tokens<'a>>)>,
+}
+
+impl<'a> Assembler<'a> {
+    fn line(&mut self, mut tokens: Tokens<'a>) -> Result<(), ParseError> {
+        let labels = tokens.labels();
+        match tokens.peek() {
+            Some((_, s)) if s.starts_with('.') => self.directive(labels, tokens),
+            Some((span, _)) if self.section == Section::Data => {
+                Err(ParseError::InstructionInData { span })
+            }
+            None if self.section == Section::Data => {
+                self.define(labels);
+                Ok(())
+            }
+            Some(_) => {
+                self.text.push((labels, Some(tokens)));
+                Ok(())
+            }
+            None => {
+                self.text.push((labels, None));
+                Ok(())
+            }
+        }
+    }
+
+    fn directive(
+        &mut self,
+        labels: Vec<Label<'a>>,
+        mut tokens: Tokens<'a>,
+    ) -> Result<(), ParseError> {
+        let (span, directive) = tokens.next("directive")?;
+        match directive.to_lowercase().as_str() {
+            ".text" => {
+                self.section = Section::Text;
+                self.text.push((labels, None));
+            }
+            ".data" => {
+                self.section = Section::Data;
+                self.text.push((labels, None));
+                if tokens.peek().is_some() {
+                    match tokens.integer("address")? {
+                        (_, addr) if addr >= 0 => self.addr = addr,
+                        (span, addr) => {
+                            return Err(ParseError::BadValue {
+                                value: addr.to_string(),
+                                span,
+                            })
+                        }
+                    }
+                }
+            }
+            ".double" => {
+                self.define(labels);
+                loop {
+                    let (_, value) = tokens.float("value")?;
+                    self.program.mem.write(self.addr, value);
+                    self.addr += 8;
+                    if tokens.peek().is_none() {
+                        break;
+                    }
+                }
+            }
+            ".word" => {
+                self.define(labels);
+                loop {
+                    let (_, value) = tokens.integer("value")?;
+                    self.program.mem.write(self.addr, value as f64);
+                    self.addr += 8;
+                    if tokens.peek().is_none() {
+                        break;
+                    }
+                }
+            }
+            ".reg" => {
+                self.text.push((labels, None));
+                let (span, reg) = tokens.next("register")?;
+                match parse_register(reg, span, self.regs, None)? {
+                    Unit::Fu(id) => self.program.fp_regs.push((id, tokens.float("value")?.1)),
+                    Unit::Regs(id) => self.program.int_regs.push((id, tokens.integer("value")?.1)),
+                    Unit::Rs(_) => unreachable!(),
+                }
+            }
+            _ => {
+                return Err(ParseError::UnknownDirective {
+                    directive: directive.to_string(),
+                    span,
+                })
+            }
+        }
+        tokens.finish()
+    }
+
+    /// Name the current data address.
+    fn define(&mut self, labels: Vec<Label>) {
+        for (span, label) in labels {
+            match self.symbols.contains_key(label) {
+                true => self.errors.push(ParseError::DuplicateLabel {
+                    label: label.to_string(),
+                    span,
+                }),
+                false => {
+                    self.symbols.insert(label.to_string(), self.addr);
+                }
+            }
+        }
+    }
+
+    /// Parse the instructions and check the branch targets.
+    fn assemble(mut self) -> Result<Program, Vec<ParseError>> {
+        let mut targets = Vec::new();
+        for (labels, tokens) in std::mem::take(&mut self.text) {
+            let pc = self.program.insts.len();
+            for &(span, label) in labels.iter() {
+                match self.symbols.contains_key(label) || self.program.labels.contains_key(label) {
+                    true => self.errors.push(ParseError::DuplicateLabel {
+                        label: label.to_string(),
+                        span,
+                    }),
+                    false => {
+                        self.program.labels.insert(label.to_string(), pc);
+                    }
+                }
+            }
+            let Some(mut tokens) = tokens else {
+                continue;
+            };
+            match parse_inst(&mut tokens, &self.symbols, self.regs) {
+                Ok((mut inst, target)) => {
+                    inst.label = labels.last().map(|(_, label)| label.to_string());
+                    inst.pc = pc;
+                    targets.extend(target);
+                    self.program.insts.push(inst);
+                }
+                Err(e) => self.errors.push(e),
+            }
+        }
+
+        for (label, span) in targets {
+            if !self.program.labels.contains_key(&label) {
+                self.errors.push(ParseError::UndefinedLabel { label, span });
+            }
+        }
+
+        match self.errors.is_empty() {
+            true => Ok(self.program),
+            false => {
+                self.errors
+                    .sort_by_key(|e| (e.span().line, e.span().column));
+                Err(self.errors)
+            }
+        }
+    }
+}
+
+/// Parse the instruction at the remaining tokens of a line.
+fn parse_inst(
+    tokens: &mut Tokens,
+    symbols: &HashMap<String, i64>,
+    regs: RegFile,
+) -> Result<(Instruction, Option<Target>), ParseError> {
+    let (span, op) = tokens.next("opcode")?;
+    let op = op
+        .to_uppercase()
+        .parse::<Type>()
+        .map_err(|_| ParseError::UnknownOpcode {
+            op: op.to_string(),
+            span,
+        })?;
+
+    // the operands of a branch may be in either register file
+    let class = RegClass::of(op);
+    let mut target = None;
+    let inst = if op.is_branch() {
+        let src1 = tokens.operand("first source operand", symbols, regs, class)?;
+        let src2 = match op.is_branch_zero() {
+            true => value::new(0.into()),
+            false => tokens.operand("second source operand", symbols, regs, class)?,
+        };
+        let (span, label) = tokens.next("branch target")?;
+        target = Some((label.to_string(), span));
+        let mut inst = Instruction::branch(op, label.to_string());
+        inst.src1.replace(src1);
+        inst.src2.replace(src2);
+        inst
+    } else {
+        let (span, dest) = tokens.next("destination register")?;
+        let mut inst = Instruction::new(op, parse_register(dest, span, regs, class)?);
+        let (src1, src2) = match op {
+            Type::LD | Type::SD => tokens.address(symbols, regs)?,
+            _ => (
+                tokens.operand("first source operand", symbols, regs, class)?,
+                tokens.operand("second source operand", symbols, regs, class)?,
+            ),
+        };
+        inst.src1.replace(src1);
+        inst.src2.replace(src2);
+        inst
+    };
+
+    tokens.finish()?;
+    Ok((inst, target))
+}
+
+/// The tokens of a line, separated by spaces or commas, without the comment.
+struct Tokens<'a> {
+    tokens: Vec<(Span, &'a str)>,
+    pos: usize,
+    // The span right after the end of the line.
+    end: Span,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(line_no: usize, line: &'a str) -> Tokens<'a> {
+        let column = |byte: usize| line[..byte].chars().count() + 1;
+        let code = &line[..comment_start(line)];
+        let mut tokens = Vec::new();
+        let mut start = None;
+        for (i, c) in code.char_indices().chain([(code.len(), ' ')]) {
+            let separator = c.is_whitespace() || c == ',';
+            let token = match start {
+                None if !separator => {
+                    start = Some(i);
+                    None
+                }
+                Some(s) if separator => Some((s, i)),
+                // a label ends with its colon
+                Some(s) if c == ':' => Some((s, i + 1)),
+                _ => None,
+            };
+            if let Some((s, e)) = token {
+                let span = Span {
+                    line: line_no,
+                    column: column(s),
+                    len: code[s..e].chars().count(),
+                };
+                tokens.push((span, &code[s..e]));
+                start = None;
+            }
+        }
+        Tokens {
+            tokens,
+            pos: 0,
+            end: Span {
+                line: line_no,
+                column: column(code.trim_end().len()),
+                len: 1,
+            },
+        }
+    }
+
+    /// Take the labels at the start of the line, without their colons.
+    fn labels(&mut self) -> Vec<Label<'a>> {
+        let mut labels = Vec::new();
+        while let Some((span, label)) = self.peek() {
+            let Some(label) = label.strip_suffix(':') else {
+                break;
+            };
+            labels.push((span, label));
+            self.pos += 1;
+        }
+        labels
+    }
+
+    fn peek(&self) -> Option<(Span, &'a str)> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn next(&mut self, operand: &'static str) -> Result<(Span, &'a str), ParseError> {
+        let token = self.peek().ok_or(ParseError::MissingOperand {
+            operand,
+            span: self.end,
+        })?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    /// Check that every token has been used.
+    fn finish(&self) -> Result<(), ParseError> {
+        match self.peek() {
+            Some((span, operand)) => Err(ParseError::UnexpectedOperand {
+                operand: operand.to_string(),
+                span,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn operand(
+        &mut self,
+        operand: &'static str,
+        symbols: &HashMap<String, i64>,
+        regs: RegFile,
+        class: Option<RegClass>,
+    ) -> Result<Value, ParseError> {
+        let (span, s) = self.next(operand)?;
+        parse_operand(s, span, symbols, regs, class)
+    }
+
+    /// Parse the address of a load or store, written as `34(R2)`
+    /// or as an offset followed by the base register.
+    fn address(
+        &mut self,
+        symbols: &HashMap<String, i64>,
+        regs: RegFile,
+    ) -> Result<(Value, Value), ParseError> {
+        let int = Some(RegClass::Int);
+        let (span, s) = self.next("offset")?;
+        let Some((offset, base)) = s.strip_suffix(')').and_then(|s| s.split_once('(')) else {
+            let offset = parse_offset(s, span, symbols)?;
+            let base = self.operand("base register", symbols, regs, int)?;
+            return Ok((value::new(offset.into()), base));
+        };
+        let offset = match offset {
+            "" => 0,
+            _ => {
+                let span = Span {
+                    len: offset.chars().count(),
+                    ..span
+                };
+                parse_offset(offset, span, symbols)?
+            }
+        };
+        let span = Span {
+            column: span.column + s.chars().count() - base.chars().count() - 1,
+            len: base.chars().count(),
+            ..span
+        };
+        let base = parse_register(base, span, regs, int)?;
+        Ok((value::new(offset.into()), value::new(base.into())))
+    }
+
+    /// Parse a floating point value of a directive.
+    fn float(&mut self, operand: &'static str) -> Result<(Span, f64), ParseError> {
+        let (span, s) = self.next(operand)?;
+        match s.trim_start_matches('#').parse::<f64>() {
+            Ok(value) => Ok((span, value)),
+            Err(_) => Err(ParseError::BadValue {
+                value: s.to_string(),
+                span,
+            }),
+        }
+    }
+
+    /// Parse an integer value of a directive.
+    fn integer(&mut self, operand: &'static str) -> Result<(Span, i64), ParseError> {
+        let (span, s) = self.next(operand)?;
+        match s.trim_start_matches('#').parse::<i64>() {
+            Ok(value) => Ok((span, value)),
+            Err(_) => Err(ParseError::BadValue {
+                value: s.to_string(),
+                span,
+            }),
+        }
+    }
+}
+
+/// The byte at which the comment of a line starts, or the length of the line.
+fn comment_start(line: &str) -> usize {
+    let bytes = line.as_bytes();
+    (0..bytes.len())
+        .find(|&i| match bytes[i] {
+            b';' => true,
+            // `#` is also the prefix of an immediate value
+            b'#' => !matches!(bytes.get(i + 1), Some(b'0'..=b'9' | b'-' | b'+')),
+            _ => false,
+        })
+        .unwrap_or(line.len())
+}
+
+/// Parse an operand, which is a register, an immediate value or a data label.
+fn parse_operand(
+    s: &str,
+    span: Span,
+    symbols: &HashMap<String, i64>,
+    regs: RegFile,
+    class: Option<RegClass>,
+) -> Result<Value, ParseError> {
+    match parse_register(s, span, regs, class) {
+        Ok(reg) => Ok(value::new(reg.into())),
+        Err(e) => match parse_offset(s, span, symbols) {
+            Ok(imm) => Ok(value::new(imm.into())),
+            Err(_) if s.starts_with(|c: char| c.is_ascii_alphabetic()) => Err(e),
+            Err(e) => Err(e),
+        },
+    }
+}
+
+/// Parse an offset or immediate value, written as `34`, `#34` or `34+`,
+/// or as a data label, optionally followed by `+` or `-` and a number.
+fn parse_offset(s: &str, span: Span, symbols: &HashMap<String, i64>) -> Result<i64, ParseError> {
+    let bad = || ParseError::BadOffset {
+        offset: s.to_string(),
+        span,
+    };
+    let n = s.trim_start_matches('#').trim_end_matches('+');
+    if let Ok(n) = n.parse::<i64>() {
+        return Ok(n);
+    }
+    let (label, delta) = match n.find(['+', '-']) {
+        Some(i) => (&n[..i], n[i..].parse::<i64>().map_err(|_| bad())?),
+        None => (n, 0),
+    };
+    symbols.get(label).map(|addr| addr + delta).ok_or_else(bad)
+}
+
+/// Parse a register, `R` or `F` followed by its number, in either case.
+///
+/// The register must be in the register files and, if `class` is
+/// given, in the register file of that class.
+fn parse_register(
+    s: &str,
+    span: Span,
+    regs: RegFile,
+    class: Option<RegClass>,
+) -> Result<Unit, ParseError> {
+    let bad = || ParseError::BadRegister {
+        reg: s.to_string(),
+        span,
+    };
+    let id = s
+        .get(1..)
+        .and_then(|n| n.parse::<u8>().ok())
+        .ok_or_else(bad)?;
+    let (reg, found, index, count) = match s.as_bytes()[0].to_ascii_uppercase() {
+        b'R' => (Unit::Regs(RegId::new(id)), RegClass::Int, id, regs.int),
+        b'F' if id.is_multiple_of(2) => (Unit::Fu(FuId::new(id)), RegClass::Fp, id / 2, regs.fp),
+        b'F' => {
+            return Err(ParseError::OddFpRegister {
+                reg: s.to_string(),
+                span,
+            })
+        }
+        _ => return Err(bad()),
+    };
+    match class {
+        Some(class) if class != found => Err(ParseError::WrongRegisterClass {
+            reg: s.to_string(),
+            expected: class.name(),
+            span,
+        }),
+        _ if index as usize >= count => Err(ParseError::RegisterOutOfRange {
+            reg: s.to_string(),
+            count,
+            span,
+        }),
+        _ => Ok(reg),
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let span = self.span();
+        write!(
+            f,
+            "line {}, column {}: {}",
+            span.line,
+            span.column,
+            self.message()
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_errors() {
+        let source = "LD F6 34+ R2\n  MUL F0 F2 F4\nADDD F3 F2\nSD F6 x R1\nBNEZ R1 Loop extra\nBEQZ R1 Done";
+        let errors = parse_program(source).unwrap_err();
+        let span = |line, column, len| Span { line, column, len };
+        assert_eq!(
+            errors,
+            [
+                ParseError::UnknownOpcode {
+                    op: "MUL".to_string(),
+                    span: span(2, 3, 3)
+                },
+                ParseError::OddFpRegister {
+                    reg: "F3".to_string(),
+                    span: span(3, 6, 2)
+                },
+                ParseError::BadOffset {
+                    offset: "x".to_string(),
+                    span: span(4, 7, 1)
+                },
+                ParseError::UnexpectedOperand {
+                    operand: "extra".to_string(),
+                    span: span(5, 14, 5)
+                },
+                ParseError::UndefinedLabel {
+                    label: "Done".to_string(),
+                    span: span(6, 9, 4)
+                },
+            ]
+        );
+
+        let missing = parse_program("ADDD F2 F4").unwrap_err();
+        assert_eq!(
+            missing[0],
+            ParseError::MissingOperand {
+                operand: "second source operand",
+                span: span(1, 11, 1)
+            }
+        );
+        assert_eq!(
+            errors[0].snippet(source),
+            "error: unknown opcode `MUL`\n --> 2:3\n  |\n2 |   MUL F0 F2 F4\n  |   ^^^\n"
+        );
+    }
+
+    #[test]
+    fn test_register_checks() {
+        let config = MachineConfig::default().with_fp_regs(16).with_int_regs(32);
+        let source = "ADDD F0 F40 F2\nDADDUI R40 R1 #8\nADDD R4,R1,R2\nDADDUI F4,F2,#8\nLD F2 0(F4)\n.reg R32, 1";
+        let errors = parse_program_with(source, &config).unwrap_err();
+        let span = |line, column, len| Span { line, column, len };
+        assert_eq!(
+            errors,
+            [
+                ParseError::RegisterOutOfRange {
+                    reg: "F40".to_string(),
+                    count: 16,
+                    span: span(1, 9, 3)
+                },
+                ParseError::RegisterOutOfRange {
+                    reg: "R40".to_string(),
+                    count: 32,
+                    span: span(2, 8, 3)
+                },
+                ParseError::WrongRegisterClass {
+                    reg: "R4".to_string(),
+                    expected: "a floating point",
+                    span: span(3, 6, 2)
+                },
+                ParseError::WrongRegisterClass {
+                    reg: "F4".to_string(),
+                    expected: "an integer",
+                    span: span(4, 8, 2)
+                },
+                ParseError::WrongRegisterClass {
+                    reg: "F4".to_string(),
+                    expected: "an integer",
+                    span: span(5, 9, 2)
+                },
+                ParseError::RegisterOutOfRange {
+                    reg: "R32".to_string(),
+                    count: 32,
+                    span: span(6, 6, 3)
+                },
+            ]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "line 1, column 9: register `F40` does not exist, the machine has 16 floating point registers"
+        );
+        assert_eq!(
+            errors[2].to_string(),
+            "line 3, column 6: expected a floating point register, found `R4`"
+        );
+
+        // without a machine only the class is checked, and the
+        // operands of a branch may be in either register file
+        assert!(parse_program("ADDD F0 F40 F2\nBNEZ F8 Loop\nLoop: BEQ R1 F2 Loop").is_ok());
+    }
+
+    #[test]
+    fn test_assemble() {
+        let source = r"
+        .data 100
+        A:  .double 1.5, 2.5    ; two doubles
+        N:  .word 2, 3
+        .text
+            .reg R1, 16
+            .reg f2, #0.5
+        Loop:
+        Next:   ld F0, A-8(R1)  # comment
+            ADDD F4, F0, F2
+            SD F4 A-8+ R1
+            DADDUI R1, R1, #-8
+            BNEZ R1, Loop
+            LD F6, (R3)
+            DADDI R4, R0, N";
+        let program = parse_program(source).unwrap();
+
+        assert_eq!(program.insts.len(), 7);
+        assert_eq!(program.labels["Loop"], 0);
+        assert_eq!(program.labels["Next"], 0);
+        assert_eq!(program.insts[0].label.as_deref(), Some("Next"));
+        assert_eq!(program.insts[0].text(), "LD F00 92 R1");
+        assert_eq!(program.insts[2].text(), "SD F04 92 R1");
+        assert_eq!(program.insts[3].text(), "DADDUI R1 R1 -8");
+        assert_eq!(program.insts[5].text(), "LD F06 0 R3");
+        assert_eq!(program.insts[6].text(), "DADDI R4 R0 116");
+        assert_eq!(
+            program.mem.iter().collect::<Vec<_>>(),
+            [(100, 1.5), (108, 2.5), (116, 2.0), (124, 3.0)]
+        );
+        assert_eq!(program.fp_regs, [(FuId::new(2), 0.5)]);
+        assert_eq!(program.int_regs, [(RegId::new(1), 16)]);
+
+        let source = ".data\nLoop: .word 1.5\nADDD F0 F2 F4\n.text\n.bss 4\nLoop: ADDD F0 F2 F4";
+        let errors = parse_program(source).unwrap_err();
+        let lines = errors.iter().map(|e| e.span().line).collect::<Vec<_>>();
+        assert_eq!(lines, [2, 3, 5, 6]);
+        assert!(matches!(errors[0], ParseError::BadValue { .. }));
+        assert!(matches!(errors[1], ParseError::InstructionInData { .. }));
+        assert!(matches!(errors[2], ParseError::UnknownDirective { .. }));
+        assert!(matches!(errors[3], ParseError::DuplicateLabel { .. }));
+    }
+}
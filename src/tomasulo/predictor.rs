@@ -0,0 +1,182 @@
+use std::str::FromStr;
+
+/// A branch predictor.
+///
+/// The predictor is asked for a direction when a branch issues and
+/// is told the actual direction once the branch is resolved.
+pub trait Predictor {
+    /// Predict whether the branch at `pc` is taken.
+    fn predict(&self, pc: usize) -> bool;
+    /// Update the predictor with the actual direction of a branch.
+    fn update(&mut self, pc: usize, taken: bool);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PredictorKind {
+    /// Always predict taken.
+    Taken,
+    /// Always predict not taken.
+    NotTaken,
+    /// A table of 1-bit last-direction entries.
+    OneBit,
+    /// A table of 2-bit saturating counters.
+    TwoBit,
+    /// 2-bit saturating counters indexed by the pc xor the global history.
+    Gshare,
+}
+
+impl PredictorKind {
+    /// Build a predictor with `entries` table entries and
+    /// `history` bits of global history.
+    pub fn build(self, entries: usize, history: u32) -> Box<dyn Predictor> {
+        match self {
+            PredictorKind::Taken => Box::new(StaticPredictor(true)),
+            PredictorKind::NotTaken => Box::new(StaticPredictor(false)),
+            PredictorKind::OneBit => Box::new(OneBitPredictor(vec![false; entries])),
+            PredictorKind::TwoBit => Box::new(TwoBitPredictor(vec![Counter::default(); entries])),
+            PredictorKind::Gshare => Box::new(GsharePredictor {
+                history: 0,
+                bits: history,
+                table: vec![Counter::default(); entries],
+            }),
+        }
+    }
+}
+
+pub struct StaticPredictor(pub bool);
+
+pub struct OneBitPredictor(Vec<bool>);
+
+pub struct TwoBitPredictor(Vec<Counter>);
+
+pub struct GsharePredictor {
+    history: usize,
+    bits: u32,
+    table: Vec<Counter>,
+}
+
+/// A 2-bit saturating counter, predicting taken from 2 on.
+#[derive(Debug, Clone, Copy, Default)]
+struct Counter(u8);
+
+impl Counter {
+    #[inline]
+    fn taken(&self) -> bool {
+        self.0 >= 2
+    }
+
+    fn update(&mut self, taken: bool) {
+        self.0 = match taken {
+            true => (self.0 + 1).min(3),
+            false => self.0.saturating_sub(1),
+        };
+    }
+}
+
+impl Predictor for StaticPredictor {
+    fn predict(&self, _pc: usize) -> bool {
+        self.0
+    }
+
+    fn update(&mut self, _pc: usize, _taken: bool) {}
+}
+
+impl Predictor for OneBitPredictor {
+    fn predict(&self, pc: usize) -> bool {
+        self.0[pc % self.0.len()]
+    }
+
+    fn update(&mut self, pc: usize, taken: bool) {
+        let len = self.0.len();
+        self.0[pc % len] = taken;
+    }
+}
+
+impl Predictor for TwoBitPredictor {
+    fn predict(&self, pc: usize) -> bool {
+        self.0[pc % self.0.len()].taken()
+    }
+
+    fn update(&mut self, pc: usize, taken: bool) {
+        let len = self.0.len();
+        self.0[pc % len].update(taken);
+    }
+}
+
+impl GsharePredictor {
+    #[inline]
+    fn index(&self, pc: usize) -> usize {
+        (pc ^ self.history) % self.table.len()
+    }
+}
+
+impl Predictor for GsharePredictor {
+    fn predict(&self, pc: usize) -> bool {
+        self.table[self.index(pc)].taken()
+    }
+
+    fn update(&mut self, pc: usize, taken: bool) {
+        let index = self.index(pc);
+        self.table[index].update(taken);
+        self.history = ((self.history << 1) | taken as usize) & ((1 << self.bits) - 1);
+    }
+}
+
+impl FromStr for PredictorKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "taken" => Ok(PredictorKind::Taken),
+            "not-taken" => Ok(PredictorKind::NotTaken),
+            "1bit" => Ok(PredictorKind::OneBit),
+            "2bit" => Ok(PredictorKind::TwoBit),
+            "gshare" => Ok(PredictorKind::Gshare),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Count the mispredictions of a predictor on the outcomes of
+    /// a single branch.
+    fn mispredicts(kind: PredictorKind, outcomes: &[bool]) -> usize {
+        let mut predictor = kind.build(16, 4);
+        outcomes
+            .iter()
+            .filter(|&&taken| {
+                let predicted = predictor.predict(4);
+                predictor.update(4, taken);
+                predicted != taken
+            })
+            .count()
+    }
+
+    #[test]
+    fn test_predictors() {
+        // a loop branch taken 3 times then falling through, run twice
+        let outcomes = [true, true, true, false, true, true, true, false];
+
+        assert_eq!(mispredicts(PredictorKind::Taken, &outcomes), 2);
+        assert_eq!(mispredicts(PredictorKind::NotTaken, &outcomes), 6);
+        assert_eq!(mispredicts(PredictorKind::OneBit, &outcomes), 4);
+        assert_eq!(mispredicts(PredictorKind::TwoBit, &outcomes), 4);
+    }
+
+    #[test]
+    fn test_gshare() {
+        // a branch alternating between taken and not taken
+        let outcomes = (0..64).map(|i| i % 2 == 0).collect::<Vec<_>>();
+        let (warmup, rest) = outcomes.split_at(16);
+        let after_warmup =
+            |kind: PredictorKind| mispredicts(kind, &outcomes) - mispredicts(kind, warmup);
+
+        // the 2-bit counter never gets past weakly not taken and misses
+        // every taken branch, while gshare has an entry for each history
+        assert_eq!(after_warmup(PredictorKind::TwoBit), rest.len() / 2);
+        assert_eq!(after_warmup(PredictorKind::Gshare), 0);
+    }
+}
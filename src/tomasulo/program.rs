@@ -0,0 +1,17 @@
+use std::collections::HashMap;
+
+use super::*;
+
+/// An assembled program, ready to be loaded into an `Executer`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Program {
+    pub insts: Vec<Instruction>,
+    /// The index of the instruction named by each label.
+    pub labels: HashMap<String, usize>,
+    /// The initial content of the memory.
+    pub mem: Memory,
+    /// The initial values of the floating point registers.
+    pub fp_regs: Vec<(FuId, f64)>,
+    /// The initial values of the integer registers.
+    pub int_regs: Vec<(RegId, i64)>,
+}
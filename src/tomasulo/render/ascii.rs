@@ -0,0 +1,61 @@
+use std::io::Write;
+
+use super::{status_table, widths};
+use crate::tomasulo::*;
+
+/// Write the instruction status table of the issued instructions
+/// as a plain ASCII table, with the cycles aligned to the right.
+pub fn write_insts<W: Write>(out: &mut W, report: &CycleReport) -> std::io::Result<()> {
+    let (header, rows) = status_table(report);
+    let widths = widths(&header, &rows);
+
+    let rule = widths.iter().map(|&width| "-".repeat(width + 2));
+    let rule = format!("+{}+", rule.collect::<Vec<_>>().join("+"));
+    writeln!(out, "{rule}")?;
+    let cells = header.iter().zip(widths.iter());
+    let cells = cells.map(|(cell, &width)| format!(" {cell:<width$} "));
+    writeln!(out, "|{}|", cells.collect::<Vec<_>>().join("|"))?;
+    writeln!(out, "{rule}")?;
+    for row in rows.iter() {
+        let cells = row.iter().zip(widths.iter()).enumerate();
+        let cells = cells.map(|(i, (cell, &width))| match i {
+            0 => format!(" {cell:<width$} "),
+            _ => format!(" {cell:>width$} "),
+        });
+        writeln!(out, "|{}|", cells.collect::<Vec<_>>().join("|"))?;
+    }
+    writeln!(out, "{rule}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tomasulo::render::run;
+
+    #[test]
+    fn test_ascii() {
+        // the mispredicted branch squashes the ADDD and the first SD,
+        // which are left out of the table
+        let config = MachineConfig::default()
+            .with_eval_mode(EvalMode::Numeric)
+            .with_rob_size(4);
+        let report = run(
+            ".reg R1, 1\nBNEZ R1 Done\nADDD F0 F2 F4\nDone: SD F2 0 R2",
+            config,
+        );
+
+        let mut out = Vec::new();
+        write_insts(&mut out, &report).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
++--------------+-------+-------+----------+-------+--------+
+| Instruction  | Issue | Start | Complete | Write | Commit |
++--------------+-------+-------+----------+-------+--------+
+| BNEZ R1 Done |     1 |     2 |        2 |     3 |      4 |
+| SD F02 0 R2  |     4 |     5 |        6 |     7 |      8 |
++--------------+-------+-------+----------+-------+--------+
+"
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::io::Write;
+
+use console::style;
+
+use crate::tomasulo::*;
+
+/// Print the state of each cycle with colors, as a table per unit.
+pub struct ConsoleRenderer<W: Write> {
+    out: W,
+}
+
+impl<W: Write> ConsoleRenderer<W> {
+    pub fn new(out: W) -> ConsoleRenderer<W> {
+        ConsoleRenderer { out }
+    }
+
+    /// Print the state of the machine at the end of a cycle.
+    pub fn render(&mut self, report: &CycleReport) -> std::io::Result<()> {
+        let out = &mut self.out;
+        writeln!(out, "{:=^60}", style("=").bold())?;
+
+        let finished = if report.finished {
+            style("> Finished").green().bold()
+        } else {
+            style("> Running").red().bold()
+        };
+        writeln!(
+            out,
+            "{} {} {}",
+            style("Cycle:").yellow().bold(),
+            style(report.cycle).cyan().bold(),
+            finished
+        )?;
+
+        writeln!(out, "{}", style("Reservation Stations:").yellow().bold())?;
+        for rs in report.rs.iter() {
+            writeln!(out, "{rs}")?;
+        }
+        writeln!(out)?;
+
+        if !report.units.is_empty() {
+            writeln!(out, "{}", style("Execution Units:").yellow().bold())?;
+            for unit in report.units.iter() {
+                writeln!(out, "{unit}")?;
+            }
+            writeln!(out)?;
+        }
+
+        if !report.mshrs.is_empty() {
+            writeln!(out, "{}", style("MSHRs:").yellow().bold())?;
+            for mshr in report.mshrs.iter() {
+                writeln!(
+                    out,
+                    "block {} : ready at cycle {}",
+                    style(mshr.block).magenta(),
+                    style(mshr.ready).cyan()
+                )?;
+            }
+            writeln!(out)?;
+        }
+
+        if let Some(rob) = report.rob.as_ref() {
+            writeln!(out, "{}", style("Reorder Buffer:").yellow().bold())?;
+            for entry in rob.iter() {
+                writeln!(out, "{entry}")?;
+            }
+            writeln!(out)?;
+        }
+
+        writeln!(out, "{}", style("Floating Units:").yellow().bold())?;
+        writeln!(out, "{:?}", report.fu)?;
+        if report.int.is_renamed() {
+            writeln!(out, "{}", style("Integer Units:").yellow().bold())?;
+            writeln!(out, "{:?}", report.int)?;
+        }
+        if report.mem.iter().next().is_some() {
+            writeln!(out, "{}", style("Memory:").yellow().bold())?;
+            writeln!(out, "{:?}", report.mem)?;
+        }
+        if report.branches > 0 {
+            writeln!(
+                out,
+                "{} {} ({} mispredicted)",
+                style("Branches:").yellow().bold(),
+                report.branches,
+                report.mispredicts
+            )?;
+        }
+        let conflicts = report
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                Event::CdbConflict { rs, .. } => Some(rs.name()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if !conflicts.is_empty() {
+            writeln!(
+                out,
+                "{} {}",
+                style("CDB conflicts:").yellow().bold(),
+                conflicts.join(", ")
+            )?;
+        }
+
+        self.render_insts(report)?;
+
+        writeln!(self.out, "{:=^60}\n", style("=").bold())
+    }
+
+    /// Print the timing of the completed instructions.
+    pub fn render_insts(&mut self, report: &CycleReport) -> std::io::Result<()> {
+        writeln!(self.out, "{}", style("Instructions:").yellow().bold())?;
+        let mut insts = report
+            .insts
+            .iter()
+            .filter(|inst| report.is_completed(inst))
+            .collect::<Vec<_>>();
+        insts.sort_by_key(|inst| inst.emit_cycle.unwrap_or(0));
+        for inst in insts {
+            // the commit column is only there with a reorder buffer
+            match report.rob {
+                Some(_) => writeln!(self.out, "{inst:#}")?,
+                None => writeln!(self.out, "{inst}")?,
+            }
+        }
+        Ok(())
+    }
+}
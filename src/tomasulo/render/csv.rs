@@ -0,0 +1,50 @@
+use std::io::Write;
+
+use super::status_table;
+use crate::tomasulo::*;
+
+/// Write the instruction status table of the issued instructions as
+/// CSV, with a header row and the pc of each instruction first.
+/// Cycles which were not reached yet are left empty.
+pub fn write_insts<W: Write>(out: &mut W, report: &CycleReport) -> std::io::Result<()> {
+    let (header, rows) = status_table(report);
+    writeln!(out, "pc,{}", header.join(","))?;
+    for (inst, row) in report.insts.iter().zip(rows.iter()) {
+        let cells = row.iter().map(|cell| field(cell));
+        writeln!(out, "{},{}", inst.pc, cells.collect::<Vec<_>>().join(","))?;
+    }
+    Ok(())
+}
+
+/// Quote a field if it contains a separator, a quote or a newline.
+pub fn field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tomasulo::render::run_for;
+
+    #[test]
+    fn test_csv() {
+        // in cycle 3 the ADDD is still waiting for the LD
+        let report = run_for("LD F6 34+ R2\nADDD F0 F6 F2", MachineConfig::default(), 3);
+
+        let mut out = Vec::new();
+        write_insts(&mut out, &report).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+pc,Instruction,Issue,Start,Complete,Write
+0,LD F06 34 R2,1,2,3,
+1,ADDD F00 F06 F02,2,,,
+"
+        );
+        assert_eq!(field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+}
@@ -0,0 +1,218 @@
+use std::io::Write;
+
+use super::status_cycles;
+use crate::tomasulo::*;
+
+/// Write the instruction status table of the issued instructions as a
+/// JSON array, with an object per instruction keyed by the lowercase
+/// column names and the pc of the instruction.
+///
+/// Cycles which were not reached yet are written as `null`.
+pub fn write_insts<W: Write>(out: &mut W, report: &CycleReport) -> std::io::Result<()> {
+    writeln!(out, "[")?;
+    let (header, rows) = status_cycles(report);
+    let keys = header
+        .iter()
+        .skip(1)
+        .map(|name| string(&name.to_lowercase()));
+    let keys = keys.collect::<Vec<_>>();
+    for (i, (inst, cycles)) in rows.iter().enumerate() {
+        let sep = if i + 1 < rows.len() { "," } else { "" };
+        let cycles = keys
+            .iter()
+            .zip(cycles.iter())
+            .map(|(key, &cycle)| format!(", {key}: {}", number(cycle)));
+        writeln!(
+            out,
+            "  {{\"pc\": {}, \"instruction\": {}{}}}{sep}",
+            inst.pc,
+            string(&inst.text()),
+            cycles.collect::<String>(),
+        )?;
+    }
+    writeln!(out, "]")
+}
+
+/// Write the state of a cycle as a single line of JSON, so that the
+/// reports of a run form a JSON Lines trace.
+///
+/// The record has the reservation stations with their operands and
+/// address, the status of the floating point registers, and the timing
+/// of every issued instruction. Numbers are written as numbers and
+/// symbolic values as their expression. Infinities and NaN, which JSON
+/// can not represent, are written as the strings `"inf"`, `"-inf"` and
+/// `"NaN"`, so that they are not mistaken for a missing value.
+pub fn write_cycle<W: Write>(out: &mut W, report: &CycleReport) -> std::io::Result<()> {
+    let value = |v: Option<&Value>| match v {
+        Some(v) => match **v {
+            ValueInner::Imm(_) | ValueInner::Float(_) => float(v.eval()),
+            _ => string(&v.to_string()),
+        },
+        None => String::from("null"),
+    };
+    let tag = |tag: Option<&Tag>| match tag {
+        Some(tag) => string(&tag.name()),
+        None => String::from("null"),
+    };
+
+    let rs = report.rs.iter().map(|rs| {
+        let op = rs.inst().map(|inst| string(&format!("{:?}", inst.op)));
+        format!(
+            "{{\"name\": {}, \"state\": {}, \"op\": {}, \"vj\": {}, \"vk\": {}, \"qj\": {}, \"qk\": {}, \"a\": {}}}",
+            string(&rs.id.name()),
+            string(&format!("{:?}", rs.state)),
+            op.unwrap_or_else(|| String::from("null")),
+            value(rs.vj.as_ref()),
+            value(rs.vk.as_ref()),
+            tag(rs.qj.as_ref()),
+            tag(rs.qk.as_ref()),
+            value(rs.addr.as_ref()),
+        )
+    });
+    let rs = rs.collect::<Vec<_>>();
+    let regs = report.fu.inner.iter().enumerate().map(|(i, fu)| {
+        format!(
+            "{{\"reg\": {}, \"qi\": {}, \"value\": {}}}",
+            string(&FuId::new(i as u8 * 2).to_string()),
+            tag(fu.qi.as_ref()),
+            value(fu.value.as_ref()),
+        )
+    });
+    let regs = regs.collect::<Vec<_>>();
+    let insts = report.insts.iter().map(|inst| {
+        format!(
+            "{{\"seq\": {}, \"pc\": {}, \"inst\": {}, \"issue\": {}, \"start\": {}, \"exec\": {}, \"write\": {}, \"commit\": {}}}",
+            inst.seq,
+            inst.pc,
+            string(&inst.text()),
+            number(inst.emit_cycle),
+            number(inst.start_cycle),
+            number(inst.exec_cycle),
+            number(inst.write_cycle),
+            number(inst.commit_cycle),
+        )
+    });
+    let insts = insts.collect::<Vec<_>>();
+    writeln!(
+        out,
+        "{{\"cycle\": {}, \"finished\": {}, \"rs\": [{}], \"regs\": [{}], \"insts\": [{}]}}",
+        report.cycle,
+        report.finished,
+        rs.join(", "),
+        regs.join(", "),
+        insts.join(", "),
+    )
+}
+
+/// Quote and escape a JSON string.
+pub fn string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Format an optional integer, with `null` for none.
+pub fn number<T: std::fmt::Display>(n: Option<T>) -> String {
+    match n {
+        Some(n) => n.to_string(),
+        None => String::from("null"),
+    }
+}
+
+/// Format an optional float, with `null` for none and a string for
+/// the values which are not finite.
+pub fn float(n: Option<f64>) -> String {
+    match n {
+        Some(n) if n.is_nan() => string("NaN"),
+        Some(n) if n.is_infinite() && n > 0.0 => string("inf"),
+        Some(n) if n.is_infinite() => string("-inf"),
+        n => number(n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tomasulo::parser::parse_program;
+    use crate::tomasulo::render::{run, run_for};
+
+    #[test]
+    fn test_cycle() {
+        let report = run_for("LD F6 34+ R2\nADDD F0 F6 F2", MachineConfig::default(), 2);
+
+        let mut out = Vec::new();
+        write_cycle(&mut out, &report).unwrap();
+        let line = String::from_utf8(out).unwrap();
+        assert_eq!(line.lines().count(), 1);
+        assert!(line.starts_with("{\"cycle\": 2, \"finished\": false, \"rs\": ["));
+        assert!(line.contains(
+            "{\"name\": \"ADD0\", \"state\": \"Busy\", \"op\": \"ADDD\", \"vj\": null, \"vk\": 2, \"qj\": \"LOAD0\", \"qk\": null, \"a\": null}"
+        ));
+        assert!(line.contains("{\"reg\": \"F00\", \"qi\": \"ADD0\", \"value\": null}"));
+        assert!(line.contains(
+            "{\"seq\": 0, \"pc\": 0, \"inst\": \"LD F06 34 R2\", \"issue\": 1, \"start\": 2, \"exec\": null, \"write\": null, \"commit\": null}"
+        ));
+    }
+
+    #[test]
+    fn test_insts() {
+        let config = MachineConfig::default().with_rob_size(4);
+        let report = run("LD F6 34+ R2\nSD F6 0 R3", config);
+
+        let mut out = Vec::new();
+        write_insts(&mut out, &report).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+[
+  {\"pc\": 0, \"instruction\": \"LD F06 34 R2\", \"issue\": 1, \"start\": 2, \"complete\": 3, \"write\": 4, \"commit\": 5},
+  {\"pc\": 1, \"instruction\": \"SD F06 0 R3\", \"issue\": 2, \"start\": 5, \"complete\": 6, \"write\": 7, \"commit\": 8}
+]
+"
+        );
+    }
+
+    #[test]
+    fn test_non_finite() {
+        assert_eq!(float(Some(1.5)), "1.5");
+        assert_eq!(float(Some(f64::INFINITY)), "\"inf\"");
+        assert_eq!(float(Some(f64::NEG_INFINITY)), "\"-inf\"");
+        assert_eq!(float(Some(f64::NAN)), "\"NaN\"");
+        assert_eq!(float(None), "null");
+
+        let program = parse_program(".reg F2, 1e308\nMULTD F0 F2 F2\nADDD F4 F0 F0").unwrap();
+        let config = MachineConfig::default().with_eval_mode(EvalMode::Numeric);
+        let mut executer = executer::Executer::new(config).unwrap();
+        executer.load(&program).unwrap();
+        let mut out = Vec::new();
+        while !executer.finished {
+            write_cycle(&mut out, &executer.step().unwrap()).unwrap();
+        }
+        let trace = String::from_utf8(out).unwrap();
+        // the ADDD first waits for the product, then gets the overflowed
+        // value as both operands
+        assert!(trace.contains(
+            "\"op\": \"ADDD\", \"vj\": null, \"vk\": null, \"qj\": \"MULT0\", \"qk\": \"MULT0\""
+        ));
+        assert!(trace.contains(
+            "\"op\": \"ADDD\", \"vj\": \"inf\", \"vk\": \"inf\", \"qj\": null, \"qk\": null"
+        ));
+    }
+
+    #[test]
+    fn test_string() {
+        assert_eq!(string("LD F6 34 R2"), "\"LD F6 34 R2\"");
+        assert_eq!(string("a\"b\\c\n"), "\"a\\\"b\\\\c\\n\"");
+    }
+}
@@ -0,0 +1,149 @@
+use std::io::Write;
+
+use crate::tomasulo::*;
+
+/// Write the lifetime of the issued instructions as a Kanata log,
+/// the format of the Konata pipeline viewer.
+///
+/// Each instruction goes through the stages `Is` (waiting in its
+/// reservation station), `Ex` (executing), `Wt` (waiting for a CDB),
+/// `Wb` (writing its result) and `Cm` (committing) with a reorder
+/// buffer, and retires in the cycle after its last stage. Squashed
+/// instructions are flushed in the cycle they were squashed in.
+pub fn write_insts<W: Write>(out: &mut W, report: &CycleReport) -> std::io::Result<()> {
+    // (cycle, line) in the order they are emitted within a cycle
+    let mut commands = Vec::new();
+    for (id, inst) in report.all_insts().into_iter().enumerate() {
+        let Some(issue) = inst.emit_cycle else {
+            continue;
+        };
+        commands.push((issue, format!("I\t{id}\t{}\t0", inst.seq)));
+        commands.push((issue, format!("L\t{id}\t0\t{}: {}", inst.pc, inst.text())));
+        commands.push((issue, format!("L\t{id}\t1\t{}", timing(inst))));
+
+        let mut stages = vec![("Is", issue)];
+        if let Some(start) = inst.start_cycle {
+            stages.push(("Ex", start));
+        }
+        if let (Some(exec), Some(write)) = (inst.exec_cycle, inst.write_cycle) {
+            if write > exec + 1 {
+                stages.push(("Wt", exec + 1));
+            }
+            stages.push(("Wb", write));
+        }
+        if let Some(commit) = inst.commit_cycle {
+            stages.push(("Cm", commit));
+        }
+        for (i, &(stage, cycle)) in stages.iter().enumerate() {
+            if i > 0 {
+                let (prev, _) = stages[i - 1];
+                commands.push((cycle, format!("E\t{id}\t0\t{prev}")));
+            }
+            commands.push((cycle, format!("S\t{id}\t0\t{stage}")));
+        }
+
+        let (last, cycle) = stages[stages.len() - 1];
+        if let Some(squash) = inst.squash_cycle {
+            commands.push((squash, format!("E\t{id}\t0\t{last}")));
+            commands.push((squash, format!("R\t{id}\t{id}\t1")));
+        } else if report.is_completed(inst) {
+            commands.push((cycle + 1, format!("E\t{id}\t0\t{last}")));
+            commands.push((cycle + 1, format!("R\t{id}\t{id}\t0")));
+        }
+    }
+    // keep the order within a cycle
+    commands.sort_by_key(|&(cycle, _)| cycle);
+
+    writeln!(out, "Kanata\t0004")?;
+    let mut now = commands.first().map_or(0, |&(cycle, _)| cycle);
+    writeln!(out, "C=\t{now}")?;
+    for (cycle, line) in commands {
+        if cycle > now {
+            writeln!(out, "C\t{}", cycle - now)?;
+            now = cycle;
+        }
+        writeln!(out, "{line}")?;
+    }
+    Ok(())
+}
+
+/// The cycles of an instruction, shown when hovering over it.
+fn timing(inst: &Instruction) -> String {
+    let cycle = |c: Option<u64>| c.map_or(String::from("-"), |c| c.to_string());
+    format!(
+        "issue {} start {} exec {} write {} commit {}",
+        cycle(inst.emit_cycle),
+        cycle(inst.start_cycle),
+        cycle(inst.exec_cycle),
+        cycle(inst.write_cycle),
+        cycle(inst.commit_cycle),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tomasulo::render::run;
+
+    #[test]
+    fn test_kanata() {
+        let report = run("LD F6 34+ R2\nADDD F0 F6 F2", MachineConfig::default());
+
+        let mut out = Vec::new();
+        write_insts(&mut out, &report).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+Kanata\t0004
+C=\t1
+I\t0\t0\t0
+L\t0\t0\t0: LD F06 34 R2
+L\t0\t1\tissue 1 start 2 exec 3 write 4 commit -
+S\t0\t0\tIs
+C\t1
+E\t0\t0\tIs
+S\t0\t0\tEx
+I\t1\t1\t0
+L\t1\t0\t1: ADDD F00 F06 F02
+L\t1\t1\tissue 2 start 5 exec 6 write 7 commit -
+S\t1\t0\tIs
+C\t2
+E\t0\t0\tEx
+S\t0\t0\tWb
+C\t1
+E\t0\t0\tWb
+R\t0\t0\t0
+E\t1\t0\tIs
+S\t1\t0\tEx
+C\t2
+E\t1\t0\tEx
+S\t1\t0\tWb
+C\t1
+E\t1\t0\tWb
+R\t1\t1\t0
+"
+        );
+    }
+
+    #[test]
+    fn test_kanata_flush() {
+        let config = MachineConfig::default()
+            .with_eval_mode(EvalMode::Numeric)
+            .with_rob_size(4);
+        let report = run(
+            ".reg R1, 1\nBNEZ R1 Done\nADDD F0 F2 F4\nDone: SD F2 0 R2",
+            config,
+        );
+
+        let mut out = Vec::new();
+        write_insts(&mut out, &report).unwrap();
+        let log = String::from_utf8(out).unwrap();
+        // the ADDD and the first SD are flushed when the branch commits
+        let retired = log.lines().filter(|line| line.starts_with('R'));
+        assert_eq!(
+            retired.collect::<Vec<_>>(),
+            ["R\t1\t1\t1", "R\t2\t2\t1", "R\t0\t0\t0", "R\t3\t3\t0"]
+        );
+        assert!(log.contains("E\t1\t0\tEx\nR\t1\t1\t1\nE\t2\t0\tIs\nR\t2\t2\t1\n"));
+    }
+}
@@ -0,0 +1,162 @@
+use std::io::Write;
+
+use super::status_table;
+use crate::tomasulo::*;
+
+/// Write the three tables of the textbook for a cycle as LaTeX
+/// `tabular` environments: the instruction status, the reservation
+/// stations and the register result status.
+pub fn write_cycle<W: Write>(out: &mut W, report: &CycleReport) -> std::io::Result<()> {
+    writeln!(out, "% cycle {}", report.cycle)?;
+    write_insts(out, report)?;
+    writeln!(out)?;
+    write_rs(out, report)?;
+    writeln!(out)?;
+    write_regs(out, report)
+}
+
+/// Write the instruction status table of the issued instructions.
+pub fn write_insts<W: Write>(out: &mut W, report: &CycleReport) -> std::io::Result<()> {
+    let (header, rows) = status_table(report);
+    let columns = format!("l{}", "c".repeat(header.len() - 1));
+    write_table(out, &columns, &header, &rows)
+}
+
+/// Write the reservation stations, with their operands, the tags
+/// they wait on and the address of loads and stores.
+pub fn write_rs<W: Write>(out: &mut W, report: &CycleReport) -> std::io::Result<()> {
+    let header = ["Name", "Busy", "Op", "Vj", "Vk", "Qj", "Qk", "A"];
+    let value = |v: Option<&Value>| v.map(|v| v.to_string()).unwrap_or_default();
+    let tag = |tag: Option<&Tag>| tag.map(|tag| tag.name()).unwrap_or_default();
+    let rows = report
+        .rs
+        .iter()
+        .map(|rs| {
+            let busy = rs.state != RsState::Free;
+            vec![
+                rs.id.name(),
+                String::from(if busy { "Yes" } else { "No" }),
+                rs.inst()
+                    .map(|inst| format!("{:?}", inst.op))
+                    .unwrap_or_default(),
+                value(rs.vj.as_ref()),
+                value(rs.vk.as_ref()),
+                tag(rs.qj.as_ref()),
+                tag(rs.qk.as_ref()),
+                value(rs.addr.as_ref()),
+            ]
+        })
+        .collect::<Vec<_>>();
+    write_table(out, "lcllllll", &header, &rows)
+}
+
+/// Write the register result status: the tag of the result each
+/// floating point register waits for.
+pub fn write_regs<W: Write>(out: &mut W, report: &CycleReport) -> std::io::Result<()> {
+    let names = (0..report.fu.inner.len()).map(|i| FuId::new(i as u8 * 2).to_string());
+    let mut header = vec![String::from("Field")];
+    header.extend(names);
+    let mut row = vec![String::from("Qi")];
+    row.extend(
+        report
+            .fu
+            .inner
+            .iter()
+            .map(|fu| fu.qi.map(|qi| qi.name()).unwrap_or_default()),
+    );
+    let header = header.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+    let columns = format!("l{}", "c".repeat(header.len() - 1));
+    write_table(out, &columns, &header, &[row])
+}
+
+fn write_table<W: Write>(
+    out: &mut W,
+    columns: &str,
+    header: &[&str],
+    rows: &[Vec<String>],
+) -> std::io::Result<()> {
+    writeln!(out, "\\begin{{tabular}}{{{columns}}}")?;
+    writeln!(out, "\\hline")?;
+    let header = header.iter().map(|cell| escape(cell));
+    writeln!(out, "{} \\\\", header.collect::<Vec<_>>().join(" & "))?;
+    writeln!(out, "\\hline")?;
+    for row in rows.iter() {
+        let cells = row.iter().map(|cell| escape(cell));
+        writeln!(out, "{} \\\\", cells.collect::<Vec<_>>().join(" & "))?;
+    }
+    writeln!(out, "\\hline")?;
+    writeln!(out, "\\end{{tabular}}")
+}
+
+/// Escape the characters which are special in LaTeX.
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '#' | '$' | '%' | '&' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tomasulo::render::run_for;
+
+    #[test]
+    fn test_latex() {
+        assert_eq!(escape("a_b & 50%"), "a\\_b \\& 50\\%");
+
+        // in cycle 2 the ADDD waits for the LD
+        let config = MachineConfig::default()
+            .with_rs_count(RsType::MULT, 0)
+            .with_rs_count(RsType::STORE, 0)
+            .with_rs_count(RsType::BRANCH, 0)
+            .with_rs_count(RsType::INT, 0)
+            .with_rs_count(RsType::ADD, 1)
+            .with_rs_count(RsType::LOAD, 1)
+            .with_fp_regs(4);
+        let report = run_for("LD F6 34+ R2\nADDD F0 F6 F2", config, 2);
+
+        let mut out = Vec::new();
+        write_cycle(&mut out, &report).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r"% cycle 2
+\begin{tabular}{lcccc}
+\hline
+Instruction & Issue & Start & Complete & Write \\
+\hline
+LD F06 34 R2 & 1 & 2 &  &  \\
+ADDD F00 F06 F02 & 2 &  &  &  \\
+\hline
+\end{tabular}
+
+\begin{tabular}{lcllllll}
+\hline
+Name & Busy & Op & Vj & Vk & Qj & Qk & A \\
+\hline
+ADD0 & Yes & ADDD &  & 2.00 & LOAD0 &  &  \\
+LOAD0 & Yes & LD &  & R2 &  &  & 34 \\
+\hline
+\end{tabular}
+
+\begin{tabular}{lcccc}
+\hline
+Field & F00 & F02 & F04 & F06 \\
+\hline
+Qi & ADD0 &  &  & LOAD0 \\
+\hline
+\end{tabular}
+"
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use std::io::Write;
+
+use super::{status_table, widths};
+use crate::tomasulo::*;
+
+/// Write the instruction status table of the issued instructions
+/// as a GitHub Markdown table, with the cycles aligned to the right.
+pub fn write_insts<W: Write>(out: &mut W, report: &CycleReport) -> std::io::Result<()> {
+    let (header, rows) = status_table(report);
+    let widths = widths(&header, &rows);
+
+    let cells = header.iter().zip(widths.iter());
+    let cells = cells.map(|(cell, &width)| format!("{cell:<width$}"));
+    writeln!(out, "| {} |", cells.collect::<Vec<_>>().join(" | "))?;
+    let rules = widths.iter().enumerate().map(|(i, &width)| match i {
+        0 => "-".repeat(width),
+        _ => format!("{}:", "-".repeat(width - 1)),
+    });
+    writeln!(out, "| {} |", rules.collect::<Vec<_>>().join(" | "))?;
+    for row in rows.iter() {
+        let cells = row.iter().zip(widths.iter()).enumerate();
+        let cells = cells.map(|(i, (cell, &width))| match i {
+            0 => format!("{cell:<width$}"),
+            _ => format!("{cell:>width$}"),
+        });
+        writeln!(out, "| {} |", cells.collect::<Vec<_>>().join(" | "))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tomasulo::render::run;
+
+    #[test]
+    fn test_markdown() {
+        // with a reorder buffer the table has a commit column
+        let config = MachineConfig::default().with_rob_size(4);
+        let report = run("LD F6 34+ R2\nADDD F0 F6 F2", config);
+
+        let mut out = Vec::new();
+        write_insts(&mut out, &report).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+| Instruction      | Issue | Start | Complete | Write | Commit |
+| ---------------- | ----: | ----: | -------: | ----: | -----: |
+| LD F06 34 R2     |     1 |     2 |        3 |     4 |      5 |
+| ADDD F00 F06 F02 |     2 |     5 |        6 |     7 |      8 |
+"
+        );
+    }
+}
@@ -0,0 +1,90 @@
+pub mod ascii;
+pub mod console;
+pub mod csv;
+pub mod json;
+pub mod kanata;
+pub mod latex;
+pub mod markdown;
+pub mod o3;
+pub mod svg;
+
+pub use console::ConsoleRenderer;
+
+use crate::tomasulo::*;
+
+/// The instruction status table of the issued instructions in issue
+/// order: the header and a row per instruction, with the commit cycle
+/// only when there is a reorder buffer. Cycles which were not reached
+/// yet are left empty.
+fn status_table(report: &CycleReport) -> (Vec<&'static str>, Vec<Vec<String>>) {
+    let (header, rows) = status_cycles(report);
+    let cycle = |c: Option<u64>| c.map(|c| c.to_string()).unwrap_or_default();
+    let rows = rows
+        .into_iter()
+        .map(|(inst, cycles)| {
+            let mut row = vec![inst.text()];
+            row.extend(cycles.into_iter().map(cycle));
+            row
+        })
+        .collect();
+    (header, rows)
+}
+
+/// A row of the instruction status table, as the instruction and
+/// the cycles of the columns after the first one.
+type StatusRow<'a> = (&'a Instruction, Vec<Option<u64>>);
+
+/// The header and the rows of the instruction status table.
+fn status_cycles(report: &CycleReport) -> (Vec<&'static str>, Vec<StatusRow<'_>>) {
+    let mut header = vec!["Instruction", "Issue", "Start", "Complete", "Write"];
+    if report.rob.is_some() {
+        header.push("Commit");
+    }
+    let rows = report
+        .insts
+        .iter()
+        .map(|inst| {
+            let mut cycles = vec![
+                inst.emit_cycle,
+                inst.start_cycle,
+                inst.exec_cycle,
+                inst.write_cycle,
+            ];
+            if report.rob.is_some() {
+                cycles.push(inst.commit_cycle);
+            }
+            (inst, cycles)
+        })
+        .collect();
+    (header, rows)
+}
+
+/// The width of each column of a table.
+fn widths(header: &[&str], rows: &[Vec<String>]) -> Vec<usize> {
+    (0..header.len())
+        .map(|i| {
+            let cells = rows.iter().map(|row| row[i].len());
+            cells.chain([header[i].len()]).max().unwrap_or(0)
+        })
+        .collect()
+}
+
+/// Run a program to the end and return the report of the last cycle.
+#[cfg(test)]
+fn run(source: &str, config: MachineConfig) -> CycleReport {
+    run_for(source, config, u64::MAX)
+}
+
+/// Run a program for up to `cycles` cycles and return the report
+/// of the last one.
+#[cfg(test)]
+fn run_for(source: &str, config: MachineConfig, cycles: u64) -> CycleReport {
+    let program = parser::parse_program(source).unwrap();
+    let mut executer = executer::Executer::new(config).unwrap();
+    executer.load(&program).unwrap();
+    let mut report = executer.report();
+    while !executer.finished && report.cycle < cycles {
+        report = executer.step().unwrap();
+    }
+    report
+}
@@ -0,0 +1,80 @@
+use std::io::Write;
+
+use crate::tomasulo::*;
+
+/// The ticks of a cycle, the default of gem5's `o3-pipeview.py`.
+pub const TICKS_PER_CYCLE: u64 = 1000;
+
+/// Write the lifetime of the issued instructions in the O3PipeView
+/// format of gem5, which Konata also reads.
+///
+/// The front end stages all take the issue cycle, `issue` is the
+/// cycle execution starts, `complete` the cycle it finishes and
+/// `retire` the commit cycle, or the write cycle without a reorder
+/// buffer. Stages which were not reached are at tick 0, so squashed
+/// instructions retire at tick 0, which marks them as flushed.
+pub fn write_insts<W: Write>(out: &mut W, report: &CycleReport) -> std::io::Result<()> {
+    let tick = |c: Option<u64>| c.map_or(0, |c| c * TICKS_PER_CYCLE);
+    for inst in report.all_insts() {
+        let issue = tick(inst.emit_cycle);
+        let retire = match report.rob {
+            Some(_) => tick(inst.commit_cycle),
+            None => tick(inst.write_cycle),
+        };
+        writeln!(
+            out,
+            "O3PipeView:fetch:{issue}:0x{:08x}:0:{}:{}",
+            inst.pc * 4,
+            inst.seq,
+            inst.text()
+        )?;
+        for stage in ["decode", "rename", "dispatch"] {
+            writeln!(out, "O3PipeView:{stage}:{issue}")?;
+        }
+        writeln!(out, "O3PipeView:issue:{}", tick(inst.start_cycle))?;
+        writeln!(out, "O3PipeView:complete:{}", tick(inst.exec_cycle))?;
+        let store = if inst.op == Type::SD { retire } else { 0 };
+        writeln!(out, "O3PipeView:retire:{retire}:store:{store}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tomasulo::render::run;
+
+    #[test]
+    fn test_o3() {
+        // the mispredicted branch squashes the ADDD and the first SD
+        let config = MachineConfig::default()
+            .with_eval_mode(EvalMode::Numeric)
+            .with_rob_size(4);
+        let report = run(
+            ".reg R1, 1\nBNEZ R1 Done\nADDD F0 F2 F4\nDone: SD F2 0 R2",
+            config,
+        );
+
+        let mut out = Vec::new();
+        write_insts(&mut out, &report).unwrap();
+        let log = String::from_utf8(out).unwrap();
+        let lines = log
+            .lines()
+            .filter(|line| line.contains(":fetch:") || line.contains(":retire:"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "O3PipeView:fetch:1000:0x00000000:0:0:BNEZ R1 Done",
+                "O3PipeView:retire:4000:store:0",
+                "O3PipeView:fetch:2000:0x00000004:0:1:ADDD F00 F02 F04",
+                "O3PipeView:retire:0:store:0",
+                "O3PipeView:fetch:3000:0x00000008:0:2:SD F02 0 R2",
+                "O3PipeView:retire:0:store:0",
+                "O3PipeView:fetch:4000:0x00000008:0:3:SD F02 0 R2",
+                "O3PipeView:retire:8000:store:8000",
+            ]
+        );
+        assert_eq!(log.lines().count(), 4 * 7);
+    }
+}
@@ -0,0 +1,226 @@
+use std::io::Write;
+
+use crate::tomasulo::*;
+
+const LABEL_WIDTH: u64 = 180;
+const CYCLE_WIDTH: u64 = 24;
+const ROW_HEIGHT: u64 = 22;
+const HEADER_HEIGHT: u64 = 24;
+const LEGEND_HEIGHT: u64 = 30;
+const LEGEND_WIDTH: u64 = 130;
+
+/// The phases of an instruction, with their color.
+const PHASES: [(&str, &str); 5] = [
+    ("waiting in RS", "#cfd8dc"),
+    ("executing", "#42a5f5"),
+    ("waiting for CDB", "#ffb74d"),
+    ("write back", "#66bb6a"),
+    ("commit", "#ab47bc"),
+];
+
+/// Write the lifetime of the issued instructions as a standalone SVG
+/// Gantt chart.
+///
+/// Each instruction is a row, with a segment per phase: waiting in its
+/// reservation station from issue, executing, waiting for a CDB, writing
+/// its result and committing. An arrow goes from the write of a result
+/// to the start of each instruction which waited for it on the CDB.
+pub fn write_insts<W: Write>(out: &mut W, report: &CycleReport) -> std::io::Result<()> {
+    let insts = report
+        .insts
+        .iter()
+        .filter(|inst| inst.emit_cycle.is_some())
+        .collect::<Vec<_>>();
+    let last = insts
+        .iter()
+        .flat_map(|inst| [inst.emit_cycle, inst.write_cycle, inst.commit_cycle])
+        .flatten()
+        .max()
+        .unwrap_or(0)
+        .max(report.cycle);
+    let legend = 8 + PHASES.len() as u64 * LEGEND_WIDTH;
+    let width = (LABEL_WIDTH + last * CYCLE_WIDTH + CYCLE_WIDTH).max(legend);
+    let height = HEADER_HEIGHT + insts.len() as u64 * ROW_HEIGHT + LEGEND_HEIGHT;
+    // the left edge of a cycle and the top of a row
+    let x = |cycle: u64| LABEL_WIDTH + (cycle - 1) * CYCLE_WIDTH;
+    let y = |row: usize| HEADER_HEIGHT + row as u64 * ROW_HEIGHT;
+
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" font-family=\"monospace\" font-size=\"12\">"
+    )?;
+    writeln!(
+        out,
+        "<defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"6\" markerHeight=\"6\" orient=\"auto\"><path d=\"M0,0 L10,5 L0,10 z\" fill=\"#d32f2f\"/></marker></defs>"
+    )?;
+    writeln!(
+        out,
+        "<rect width=\"{width}\" height=\"{height}\" fill=\"white\"/>"
+    )?;
+
+    for cycle in 1..=last {
+        let center = x(cycle) + CYCLE_WIDTH / 2;
+        writeln!(
+            out,
+            "<text x=\"{center}\" y=\"16\" text-anchor=\"middle\">{cycle}</text>"
+        )?;
+        writeln!(
+            out,
+            "<line x1=\"{0}\" y1=\"{HEADER_HEIGHT}\" x2=\"{0}\" y2=\"{1}\" stroke=\"#eeeeee\"/>",
+            x(cycle),
+            y(insts.len())
+        )?;
+    }
+
+    for (row, inst) in insts.iter().enumerate() {
+        let top = y(row);
+        writeln!(
+            out,
+            "<text x=\"4\" y=\"{}\">{}</text>",
+            top + ROW_HEIGHT / 2 + 4,
+            escape(&inst.text())
+        )?;
+        for (phase, from, to) in phases(inst) {
+            writeln!(
+                out,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"><title>{}</title></rect>",
+                x(from),
+                top + 3,
+                (to - from + 1) * CYCLE_WIDTH,
+                ROW_HEIGHT - 6,
+                PHASES[phase].1,
+                PHASES[phase].0
+            )?;
+        }
+    }
+
+    for (producer, consumer) in dependencies(&insts) {
+        let (from, to) = (insts[producer], insts[consumer]);
+        let (Some(write), Some(start)) = (from.write_cycle, to.start_cycle) else {
+            continue;
+        };
+        writeln!(
+            out,
+            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#d32f2f\" marker-end=\"url(#arrow)\"/>",
+            x(write) + CYCLE_WIDTH / 2,
+            y(producer) + ROW_HEIGHT / 2,
+            x(start) + 2,
+            y(consumer) + ROW_HEIGHT / 2
+        )?;
+    }
+
+    let top = y(insts.len()) + 8;
+    for (i, (name, color)) in PHASES.iter().enumerate() {
+        let left = 4 + i as u64 * LEGEND_WIDTH;
+        writeln!(
+            out,
+            "<rect x=\"{left}\" y=\"{top}\" width=\"12\" height=\"12\" fill=\"{color}\"/>"
+        )?;
+        writeln!(
+            out,
+            "<text x=\"{}\" y=\"{}\">{name}</text>",
+            left + 16,
+            top + 10
+        )?;
+    }
+    writeln!(out, "</svg>")
+}
+
+/// The phases of an instruction, as the index in `PHASES` and the
+/// first and last cycle of each one.
+fn phases(inst: &Instruction) -> Vec<(usize, u64, u64)> {
+    let mut phases = Vec::new();
+    let Some(issue) = inst.emit_cycle else {
+        return phases;
+    };
+    match inst.start_cycle {
+        Some(start) if start > issue => phases.push((0, issue, start - 1)),
+        Some(_) => {}
+        None => phases.push((0, issue, issue)),
+    }
+    if let (Some(start), Some(exec)) = (inst.start_cycle, inst.exec_cycle) {
+        phases.push((1, start, exec));
+    }
+    if let (Some(exec), Some(write)) = (inst.exec_cycle, inst.write_cycle) {
+        if write > exec + 1 {
+            phases.push((2, exec + 1, write - 1));
+        }
+        phases.push((3, write, write));
+    }
+    if let Some(commit) = inst.commit_cycle {
+        phases.push((4, commit, commit));
+    }
+    phases
+}
+
+/// The read after write dependencies resolved on the CDB, as the rows
+/// of the producer and of the consumer.
+///
+/// An instruction depends on the latest earlier one writing each of
+/// its source registers, if that one had not written its result
+/// before it was issued.
+fn dependencies(insts: &[&Instruction]) -> Vec<(usize, usize)> {
+    let mut deps = Vec::new();
+    for (i, inst) in insts.iter().enumerate() {
+        let mut sources = [inst.src1.as_ref(), inst.src2.as_ref()]
+            .into_iter()
+            .flatten()
+            .filter_map(|src| match **src {
+                ValueInner::Unit(unit) => Some(unit),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // the dest of SD is the value to be stored
+        if inst.op == Type::SD {
+            sources.extend(inst.dest);
+        }
+        for unit in sources {
+            let producer = (0..i).rev().find(|&j| {
+                let earlier = insts[j];
+                earlier.op != Type::SD && earlier.dest == Some(unit)
+            });
+            if let Some(j) = producer {
+                if insts[j].write_cycle >= inst.emit_cycle && !deps.contains(&(j, i)) {
+                    deps.push((j, i));
+                }
+            }
+        }
+    }
+    deps
+}
+
+/// Escape the characters which are special in XML text.
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tomasulo::render::run;
+
+    #[test]
+    fn test_svg() {
+        let config = MachineConfig::default().with_rob_size(4);
+        let report = run("LD F6 34+ R2\nADDD F0 F6 F2\nSD F0 0 R3", config);
+        let insts = report.insts.iter().collect::<Vec<_>>();
+        // the ADDD waits for the LD, and the SD for the value to store
+        assert_eq!(dependencies(&insts), [(0, 1), (1, 2)]);
+        // waits from cycle 3 to 7, executes in 8 and 9, writes in 10
+        // and commits in 11
+        assert_eq!(
+            phases(insts[2]),
+            [(0, 3, 7), (1, 8, 9), (3, 10, 10), (4, 11, 11)]
+        );
+
+        let mut out = Vec::new();
+        write_insts(&mut out, &report).unwrap();
+        let svg = String::from_utf8(out).unwrap();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert!(svg.contains(">ADDD F00 F06 F02</text>"));
+        assert_eq!(svg.matches("marker-end").count(), 2);
+    }
+}
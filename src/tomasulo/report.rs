@@ -0,0 +1,85 @@
+use super::*;
+
+/// A snapshot of the machine at the end of a cycle.
+#[derive(Debug, Clone)]
+pub struct CycleReport {
+    pub cycle: u64,
+    pub finished: bool,
+    /// The reservation stations, in a deterministic order.
+    pub rs: Vec<RsInner>,
+    /// The shared execution units, empty if every station has its own.
+    pub units: Vec<EuInner>,
+    /// The misses of the data cache still outstanding.
+    pub mshrs: Vec<Mshr>,
+    /// The reorder buffer from head to tail, if it is enabled.
+    pub rob: Option<Vec<RobEntry>>,
+    /// The floating point register status.
+    pub fu: FloatingUnit,
+    /// The integer register status.
+    pub int: IntegerUnit,
+    pub mem: Memory,
+    /// The issued instructions which have not been squashed, in issue order.
+    pub insts: Vec<Instruction>,
+    /// The issued instructions which have been squashed, in issue order.
+    pub squashed: Vec<Instruction>,
+    /// What happened during the cycle.
+    pub events: Vec<Event>,
+    pub branches: u64,
+    pub mispredicts: u64,
+}
+
+/// Something which happened to an instruction during a cycle.
+///
+/// Instructions are identified by their `seq`, the order in which
+/// they were issued.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The instruction was issued to a reservation station.
+    Issue { seq: usize, rs: RsId },
+    /// The load or store waited for an earlier access to the memory.
+    MemoryWait { seq: usize, rs: RsId },
+    /// The load took the value of an earlier store instead of the memory.
+    Forward { seq: usize },
+    /// The load missed in the data cache.
+    CacheMiss { seq: usize },
+    /// The load would miss in the data cache but every MSHR was taken.
+    MshrFull { seq: usize, rs: RsId },
+    /// The operands were ready but no execution unit was free.
+    NoFreeUnit { seq: usize, rs: RsId },
+    /// The instruction started executing.
+    ExecStart { seq: usize },
+    /// The instruction finished executing.
+    ExecComplete { seq: usize },
+    /// The result was broadcast on the CDB.
+    Write { seq: usize, tag: Tag },
+    /// The result was ready but no CDB was free.
+    CdbConflict { seq: usize, rs: RsId },
+    /// The instruction left the reorder buffer.
+    Commit { seq: usize },
+    /// A branch was mispredicted.
+    Mispredict { seq: usize },
+    /// Speculative instructions were squashed.
+    Squash { count: usize },
+}
+
+impl CycleReport {
+    /// Whether an instruction has completed, i.e. it has been committed
+    /// with a reorder buffer or has written its result without one.
+    pub fn is_completed(&self, inst: &Instruction) -> bool {
+        match self.rob {
+            Some(_) => inst.commit_cycle.is_some(),
+            None => inst.write_cycle.is_some(),
+        }
+    }
+
+    /// Every issued instruction, squashed or not, in issue order.
+    pub fn all_insts(&self) -> Vec<&Instruction> {
+        let mut insts = self
+            .insts
+            .iter()
+            .chain(self.squashed.iter())
+            .collect::<Vec<_>>();
+        insts.sort_by_key(|inst| inst.seq);
+        insts
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+
+use super::*;
+
+/// Why no instruction was issued in a cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Stall {
+    /// Every reservation station of the type is busy.
+    NoFreeRs(RsType),
+    /// The reorder buffer is full.
+    RobFull,
+    /// Issue waits for a branch to be resolved.
+    BranchPending,
+    /// Every instruction of the program has been issued. Only counted
+    /// in the first cycle, not while the pipeline drains.
+    QueueEmpty,
+}
+
+/// Statistics collected cycle by cycle while simulating.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    pub cycles: u64,
+    /// The instructions issued, including squashed ones.
+    pub issued: u64,
+    /// The instructions completed, i.e. committed with a reorder
+    /// buffer or written without one.
+    pub completed: u64,
+    /// The number of reservation stations of each type.
+    pub rs_count: BTreeMap<RsType, usize>,
+    /// The number of cycles each reservation station of a type was
+    /// busy, summed over the stations of the type.
+    pub rs_busy: BTreeMap<RsType, u64>,
+    /// The total cycles from issue to write and the number of
+    /// completed instructions of each opcode.
+    pub latency: BTreeMap<Type, (u64, u64)>,
+    /// The number of cycles instructions with ready operands waited
+    /// for each type of execution unit.
+    pub unit_waits: BTreeMap<EuType, u64>,
+    /// The number of cycles loads and stores waited for an earlier
+    /// access to the memory.
+    pub mem_waits: u64,
+    /// The number of loads which took their value from a store.
+    pub forwarded: u64,
+    /// The accesses to the data cache which hit and missed.
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    /// The number of memory writes of the data cache: blocks written
+    /// back or stores written through.
+    pub mem_writes: u64,
+    /// The misses which merged into an outstanding miss of their block.
+    pub mshr_merges: u64,
+    /// The number of cycles loads waited for a free MSHR.
+    pub mshr_waits: u64,
+    /// The number of cycles issue stopped before the issue width
+    /// for each cause.
+    pub stalls: BTreeMap<Stall, u64>,
+    /// The number of common data buses, `None` if it is unlimited.
+    pub cdb_count: Option<usize>,
+    /// The number of cycles a CDB was used.
+    pub cdb_busy: u64,
+    /// The number of results written on a CDB.
+    pub cdb_writes: u64,
+    /// The number of times a result waited a cycle for a free CDB.
+    pub cdb_conflicts: u64,
+}
+
+impl Stats {
+    /// Start collecting statistics of a machine with the given stations.
+    pub fn new<'a>(config: &MachineConfig, rs: impl Iterator<Item = &'a RsInner>) -> Stats {
+        let mut stats = Stats {
+            cdb_count: config.cdb_count(),
+            ..Stats::default()
+        };
+        for rs in rs {
+            *stats.rs_count.entry(rs.id.rs_type()).or_default() += 1;
+        }
+        stats
+    }
+
+    /// Count the busy stations at the end of a cycle.
+    pub(crate) fn cycle<'a>(&mut self, rs: impl Iterator<Item = &'a RsInner>, writes: usize) {
+        self.cycles += 1;
+        for rs in rs.filter(|rs| rs.state != RsState::Free) {
+            *self.rs_busy.entry(rs.id.rs_type()).or_default() += 1;
+        }
+        if writes > 0 {
+            self.cdb_busy += 1;
+            self.cdb_writes += writes as u64;
+        }
+    }
+
+    pub(crate) fn stall(&mut self, stall: Stall) {
+        *self.stalls.entry(stall).or_default() += 1;
+    }
+
+    pub(crate) fn unit_wait(&mut self, eu_type: EuType) {
+        *self.unit_waits.entry(eu_type).or_default() += 1;
+    }
+
+    pub(crate) fn cache_access(&mut self, access: CacheAccess) {
+        match access.hit {
+            true => self.cache_hits += 1,
+            false => self.cache_misses += 1,
+        }
+        self.mem_writes += access.mem_write as u64;
+        self.mshr_merges += access.merged as u64;
+    }
+
+    pub(crate) fn complete(&mut self, inst: &Instruction) {
+        self.completed += 1;
+        if let (Some(issue), Some(write)) = (inst.emit_cycle, inst.write_cycle) {
+            let latency = self.latency.entry(inst.op).or_default();
+            latency.0 += write - issue;
+            latency.1 += 1;
+        }
+    }
+
+    /// Cycles per completed instruction.
+    pub fn cpi(&self) -> Option<f64> {
+        (self.completed > 0).then(|| self.cycles as f64 / self.completed as f64)
+    }
+
+    /// Completed instructions per cycle.
+    pub fn ipc(&self) -> Option<f64> {
+        (self.cycles > 0).then(|| self.completed as f64 / self.cycles as f64)
+    }
+
+    /// The average fraction of the stations of a type which were busy.
+    pub fn occupancy(&self, rs_type: RsType) -> Option<f64> {
+        let count = self.rs_count.get(&rs_type).copied().unwrap_or(0);
+        let busy = self.rs_busy.get(&rs_type).copied().unwrap_or(0);
+        (self.cycles > 0 && count > 0).then(|| busy as f64 / (self.cycles * count as u64) as f64)
+    }
+
+    /// The average number of cycles from issue to write of an opcode.
+    pub fn avg_latency(&self, op: Type) -> Option<f64> {
+        self.latency
+            .get(&op)
+            .map(|(total, count)| *total as f64 / *count as f64)
+    }
+
+    /// The fraction of the accesses to the data cache which hit.
+    pub fn cache_hit_rate(&self) -> Option<f64> {
+        let accesses = self.cache_hits + self.cache_misses;
+        (accesses > 0).then(|| self.cache_hits as f64 / accesses as f64)
+    }
+
+    /// The fraction of the buses used per cycle, or the fraction of
+    /// cycles a bus was used if they are unlimited.
+    pub fn cdb_utilization(&self) -> Option<f64> {
+        let cycles = self.cycles as f64;
+        (self.cycles > 0).then(|| match self.cdb_count {
+            Some(count) => self.cdb_writes as f64 / (cycles * count as f64),
+            None => self.cdb_busy as f64 / cycles,
+        })
+    }
+}
+
+impl std::fmt::Display for Stall {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Stall::NoFreeRs(rs_type) => write!(f, "no free {rs_type:?} station"),
+            Stall::RobFull => write!(f, "reorder buffer full"),
+            Stall::BranchPending => write!(f, "branch pending"),
+            Stall::QueueEmpty => write!(f, "instruction queue empty"),
+        }
+    }
+}
+
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let ratio = |v: Option<f64>| v.map_or(String::from("-"), |v| format!("{v:.2}"));
+        writeln!(
+            f,
+            "Cycles: {}, Issued: {}, Completed: {}, CPI: {}, IPC: {}",
+            self.cycles,
+            self.issued,
+            self.completed,
+            ratio(self.cpi()),
+            ratio(self.ipc())
+        )?;
+
+        writeln!(f, "Station occupancy:")?;
+        for &rs_type in self.rs_count.keys() {
+            let occupancy = self.occupancy(rs_type).unwrap_or(0.0);
+            writeln!(
+                f,
+                "  {:<24}{:>6.1}%",
+                format!("{rs_type:?}"),
+                occupancy * 100.0
+            )?;
+        }
+
+        writeln!(f, "Average latency from issue to write:")?;
+        for (op, (total, count)) in self.latency.iter() {
+            let op = format!("{op:?}");
+            writeln!(f, "  {op:<24}{:>7.2}", *total as f64 / *count as f64)?;
+        }
+
+        if !self.unit_waits.is_empty() {
+            writeln!(f, "Waiting for an execution unit:")?;
+            for (eu_type, cycles) in self.unit_waits.iter() {
+                writeln!(f, "  {:<24}{cycles:>7}", format!("{eu_type:?}"))?;
+            }
+        }
+
+        if self.mem_waits > 0 {
+            writeln!(f, "Waiting for memory order: {}", self.mem_waits)?;
+        }
+        if self.forwarded > 0 {
+            writeln!(f, "Loads forwarded from stores: {}", self.forwarded)?;
+        }
+
+        if let Some(rate) = self.cache_hit_rate() {
+            writeln!(
+                f,
+                "Cache hit rate: {:.1}% ({} hits, {} misses, {} memory writes)",
+                rate * 100.0,
+                self.cache_hits,
+                self.cache_misses,
+                self.mem_writes
+            )?;
+            if self.mshr_merges > 0 || self.mshr_waits > 0 {
+                writeln!(
+                    f,
+                    "MSHRs: {} merged misses, {} cycles waited",
+                    self.mshr_merges, self.mshr_waits
+                )?;
+            }
+        }
+
+        writeln!(f, "Issue stalls:")?;
+        for (stall, cycles) in self.stalls.iter() {
+            writeln!(f, "  {:<24}{cycles:>7}", stall.to_string())?;
+        }
+
+        write!(
+            f,
+            "CDB utilization: {:.1}% ({} writes, {} conflicts)",
+            self.cdb_utilization().unwrap_or(0.0) * 100.0,
+            self.cdb_writes,
+            self.cdb_conflicts
+        )
+    }
+}
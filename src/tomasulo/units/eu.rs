@@ -0,0 +1,273 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use console::style;
+
+use crate::tomasulo::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EuId(EuType, u8);
+
+impl EuId {
+    #[inline]
+    pub fn eu_type(&self) -> EuType {
+        self.0
+    }
+
+    /// The name of the unit, such as `Adder0`, without colors.
+    pub fn name(&self) -> String {
+        format!("{:?}{}", self.0, self.1)
+    }
+}
+
+/// The kind of execution unit an instruction runs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum EuType {
+    Adder,
+    Multiplier,
+    Divider,
+    Load,
+    Store,
+    Branch,
+    Integer,
+}
+
+/// How the free execution units are given to the waiting stations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DispatchPolicy {
+    /// The instructions issued first start first.
+    #[default]
+    Oldest,
+    /// Stations start in their fixed order, e.g. `ADD0` before `ADD1`.
+    Priority,
+}
+
+#[derive(Debug, Clone)]
+pub struct EuInner {
+    pub id: EuId,
+    /// The stations executing on the unit and their instructions,
+    /// more than one if the unit is pipelined.
+    pub rs: Vec<(RsId, usize)>,
+    /// The first cycle the unit accepts a new instruction.
+    free_at: u64,
+}
+
+/// The execution units shared by the reservation stations.
+///
+/// Only the types with a configured number of units are limited;
+/// instructions of the other types start as soon as their operands
+/// are ready, as if every station had its own unit.
+///
+/// A unit accepts a new instruction every initiation interval. It is
+/// not pipelined by default, i.e. the interval is the latency of the
+/// instruction it executes.
+#[derive(Debug, Clone)]
+pub struct ExecUnits {
+    /// use BTreeMap to make the order of units deterministic.
+    inner: BTreeMap<EuId, EuInner>,
+    interval: BTreeMap<EuType, u64>,
+    policy: DispatchPolicy,
+}
+
+impl ExecUnits {
+    pub fn new(config: &MachineConfig) -> ExecUnits {
+        let (mut inner, mut interval) = (BTreeMap::new(), BTreeMap::new());
+        for eu_type in [
+            EuType::Adder,
+            EuType::Multiplier,
+            EuType::Divider,
+            EuType::Load,
+            EuType::Store,
+            EuType::Branch,
+            EuType::Integer,
+        ] {
+            if let Some(cycles) = config.eu_interval(eu_type) {
+                interval.insert(eu_type, cycles);
+            }
+            for i in 0..config.eu_count(eu_type).unwrap_or(0) {
+                let id = EuId(eu_type, i as u8);
+                inner.insert(
+                    id,
+                    EuInner {
+                        id,
+                        rs: Vec::new(),
+                        free_at: 0,
+                    },
+                );
+            }
+        }
+        ExecUnits {
+            inner,
+            interval,
+            policy: config.dispatch_policy(),
+        }
+    }
+
+    /// Whether the instructions of a type share a limited number of units.
+    pub fn is_limited(&self, eu_type: EuType) -> bool {
+        self.inner.keys().any(|id| id.0 == eu_type)
+    }
+
+    /// Iterate over the units.
+    pub fn iter(&self) -> impl Iterator<Item = &EuInner> {
+        self.inner.values()
+    }
+
+    /// Choose the stations which start executing in this cycle among
+    /// the ones with ready operands, except the `blocked` ones. Returns
+    /// the stations which start and the ones which have to wait for a
+    /// free unit.
+    ///
+    /// An instruction leaves its unit once it has finished executing.
+    /// A unit is free again after the initiation interval, or at once
+    /// if its instruction has left the station, e.g. because it was
+    /// squashed.
+    pub fn dispatch(
+        &mut self,
+        cycle: u64,
+        rs: &ReservationStation,
+        blocked: &[RsId],
+    ) -> (Vec<RsId>, Vec<RsId>) {
+        let executing = |id: RsId, seq: usize| {
+            rs.iter().any(|inner| {
+                inner.id == id
+                    && inner.inst().is_some_and(|inst| {
+                        inst.seq == seq && inst.exec_cycle.is_none_or(|c| c >= cycle)
+                    })
+            })
+        };
+        let held = |id: RsId, seq: usize| {
+            rs.iter()
+                .any(|inner| inner.id == id && inner.inst().is_some_and(|inst| inst.seq == seq))
+        };
+        for unit in self.inner.values_mut() {
+            if unit.rs.iter().any(|&(id, seq)| !held(id, seq)) {
+                unit.free_at = cycle;
+            }
+            unit.rs.retain(|&(id, seq)| executing(id, seq));
+        }
+
+        let mut waiting = rs
+            .iter()
+            .filter(|inner| inner.state == RsState::Calculating && !blocked.contains(&inner.id))
+            .filter_map(|inner| inner.inst().map(|inst| (inner.id, inst)))
+            .filter(|(_, inst)| inst.start_cycle.is_none())
+            .collect::<Vec<_>>();
+        if self.policy == DispatchPolicy::Oldest {
+            waiting.sort_by_key(|(_, inst)| inst.seq);
+        }
+
+        let (mut start, mut delayed) = (Vec::new(), Vec::new());
+        for (id, inst) in waiting {
+            let eu_type = EuType::from(inst.op);
+            if !self.is_limited(eu_type) {
+                start.push(id);
+                continue;
+            }
+            let interval = self.interval.get(&eu_type).copied();
+            let free = self
+                .inner
+                .values_mut()
+                .find(|unit| unit.id.0 == eu_type && cycle >= unit.free_at);
+            match free {
+                Some(unit) => {
+                    unit.rs.push((id, inst.seq));
+                    unit.free_at = cycle + interval.unwrap_or(inst.latency());
+                    start.push(id);
+                }
+                None => delayed.push(id),
+            }
+        }
+        (start, delayed)
+    }
+
+    /// Keep the unit of a station busy for `cycles` more cycles, when
+    /// its instruction takes longer than its latency because of a cache
+    /// miss. A pipelined unit still accepts an instruction every
+    /// initiation interval.
+    pub fn delay(&mut self, id: RsId, cycles: u64) {
+        let unit = self
+            .inner
+            .values_mut()
+            .find(|unit| unit.rs.iter().any(|&(rs, _)| rs == id));
+        if let Some(unit) = unit {
+            if !self.interval.contains_key(&unit.id.0) {
+                unit.free_at += cycles;
+            }
+        }
+    }
+
+    /// Free all units.
+    pub fn clear(&mut self) {
+        for unit in self.inner.values_mut() {
+            unit.rs.clear();
+            unit.free_at = 0;
+        }
+    }
+}
+
+impl From<Type> for EuType {
+    fn from(t: Type) -> EuType {
+        match t {
+            Type::ADDD | Type::SUBD => EuType::Adder,
+            Type::MULTD => EuType::Multiplier,
+            Type::DIVD => EuType::Divider,
+            Type::LD => EuType::Load,
+            Type::SD => EuType::Store,
+            Type::BEQ | Type::BNE | Type::BEQZ | Type::BNEZ => EuType::Branch,
+            Type::ADD | Type::SUB | Type::ADDI | Type::SUBI | Type::DADDI | Type::DADDUI => {
+                EuType::Integer
+            }
+        }
+    }
+}
+
+impl FromStr for EuType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "adder" => Ok(EuType::Adder),
+            "multiplier" => Ok(EuType::Multiplier),
+            "divider" => Ok(EuType::Divider),
+            "load" => Ok(EuType::Load),
+            "store" => Ok(EuType::Store),
+            "branch" => Ok(EuType::Branch),
+            "integer" => Ok(EuType::Integer),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromStr for DispatchPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "oldest" => Ok(DispatchPolicy::Oldest),
+            "priority" => Ok(DispatchPolicy::Priority),
+            _ => Err(()),
+        }
+    }
+}
+
+impl std::fmt::Display for EuInner {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let rs = match self.rs.is_empty() {
+            true => format!("{:<6}", style("Free").yellow().bold()),
+            false => self
+                .rs
+                .iter()
+                .map(|(id, _)| id.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        };
+        write!(f, "{} : {}", self.id, rs)
+    }
+}
+
+impl std::fmt::Display for EuId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:<11}", style(self.name()).magenta().bold())
+    }
+}
@@ -6,51 +6,83 @@ use crate::tomasulo::*;
 pub struct FuId(u8);
 
 impl FuId {
+    /// Double precision registers take two single registers, so `id` must be even.
     pub fn new(id: u8) -> FuId {
-        assert!(id % 2 == 0 && id < 2 * FU_SIZE as u8);
+        assert!(id.is_multiple_of(2));
         FuId(id)
     }
 }
 
-pub const FU_SIZE: usize = 16;
-
 #[derive(Clone, PartialEq)]
 pub struct FloatingUnit {
-    pub inner: [FloatingUnitInner; FU_SIZE],
+    pub inner: Vec<FloatingUnitInner>,
 }
 
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct FloatingUnitInner {
-    pub qi: Option<RsId>,
+    pub qi: Option<Tag>,
     pub value: Option<Value>,
 }
 
 impl FloatingUnit {
-    pub fn new() -> FloatingUnit {
+    pub fn new(size: usize) -> FloatingUnit {
         FloatingUnit {
-            inner: (0..FU_SIZE)
+            inner: (0..size)
                 .map(|v| FloatingUnitInner::default().with_value(2f64 * v as f64))
-                .collect::<Vec<_>>()
-                .try_into()
-                .unwrap(),
+                .collect(),
         }
     }
 
+    /// Set the initial value of a register.
+    pub fn set(&mut self, id: FuId, val: f64) {
+        self.inner[id.0 as usize / 2].value = Some(value::new(ValueInner::Float(val)));
+    }
+
     /// Mark the floating unit as busy.
-    pub fn mark_busy(&mut self, id: FuId, qi: RsId) {
+    ///
+    /// With a reorder buffer the register keeps its committed
+    /// value until the new result is committed.
+    pub fn mark_busy(&mut self, id: FuId, qi: Tag) {
         let fu = &mut self.inner[id.0 as usize / 2];
         fu.qi = Some(qi);
-        fu.value = None;
+        if let Tag::Rs(_) = qi {
+            fu.value = None;
+        }
     }
 
     /// Mark the floating unit as ready.
-    pub fn mark_ready(&mut self, id: FuId, qi: RsId, value: Value) {
+    pub fn mark_ready(&mut self, id: FuId, qi: Tag, value: Value) {
         let fu = &mut self.inner[id.0 as usize / 2];
         if fu.qi == Some(qi) {
             fu.value = Some(value);
         }
     }
 
+    /// Commit the value of a reorder buffer entry.
+    pub fn commit(&mut self, id: FuId, qi: RobId, value: Value) {
+        let fu = &mut self.inner[id.0 as usize / 2];
+        fu.value = Some(value);
+        if fu.qi == Some(Tag::Rob(qi)) {
+            fu.qi = None;
+        }
+    }
+
+    /// Forget the pending results of the reorder buffer,
+    /// after every speculative instruction has been squashed.
+    pub fn squash(&mut self) {
+        for fu in self.inner.iter_mut() {
+            if let Some(Tag::Rob(_)) = fu.qi {
+                fu.qi = None;
+            }
+        }
+    }
+
+    /// Whether the register file has a register.
+    #[inline]
+    pub fn contains(&self, id: FuId) -> bool {
+        (id.0 as usize / 2) < self.inner.len()
+    }
+
     pub fn get(&self, id: FuId) -> &FloatingUnitInner {
         &self.inner[id.0 as usize / 2]
     }
@@ -1,11 +1,15 @@
+pub mod eu;
 pub mod fu;
 pub mod regs;
+pub mod rob;
 pub mod rs;
 
 use std::str::FromStr;
 
+pub use eu::*;
 pub use fu::*;
 pub use regs::*;
+pub use rob::*;
 pub use rs::*;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -18,6 +22,26 @@ pub enum Unit {
     Regs(RegId),
 }
 
+/// The tag of a pending result.
+///
+/// Without a reorder buffer results are named by the reservation
+/// station producing them, otherwise by their reorder buffer entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tag {
+    Rs(RsId),
+    Rob(RobId),
+}
+
+impl Tag {
+    /// The name of the tag, without colors.
+    pub fn name(&self) -> String {
+        match self {
+            Tag::Rs(id) => id.name(),
+            Tag::Rob(id) => id.name(),
+        }
+    }
+}
+
 impl FromStr for Unit {
     type Err = ();
 
@@ -30,13 +54,10 @@ impl FromStr for Unit {
                     Err(())
                 }
             }
-            Some('F') => {
-                if let Ok(id) = s[1..].parse::<u8>() {
-                    Ok(Unit::Fu(FuId::new(id)))
-                } else {
-                    Err(())
-                }
-            }
+            Some('F') => match s[1..].parse::<u8>() {
+                Ok(id) if id.is_multiple_of(2) => Ok(Unit::Fu(FuId::new(id))),
+                _ => Err(()),
+            },
             _ => Err(()),
         }
     }
@@ -60,6 +81,27 @@ impl From<RegId> for Unit {
     }
 }
 
+impl From<RsId> for Tag {
+    fn from(id: RsId) -> Tag {
+        Tag::Rs(id)
+    }
+}
+
+impl From<RobId> for Tag {
+    fn from(id: RobId) -> Tag {
+        Tag::Rob(id)
+    }
+}
+
+impl std::fmt::Display for Tag {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Tag::Rs(id) => write!(f, "{id}"),
+            Tag::Rob(id) => write!(f, "{id}"),
+        }
+    }
+}
+
 impl std::fmt::Display for Unit {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
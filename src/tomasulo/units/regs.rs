@@ -1,3 +1,7 @@
+use console::style;
+
+use crate::tomasulo::*;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct RegId(u8);
 
@@ -5,6 +9,152 @@ impl RegId {
     pub fn new(id: u8) -> RegId {
         RegId(id)
     }
+
+    /// Whether this is `R0`, which always reads zero.
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+}
+
+/// The integer register file.
+#[derive(Clone, PartialEq)]
+pub struct IntegerUnit {
+    pub inner: Vec<IntegerUnitInner>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct IntegerUnitInner {
+    pub qi: Option<Tag>,
+    pub value: Option<Value>,
+}
+
+impl IntegerUnit {
+    /// Create the register file, where each register holds
+    /// its own name until it is given an initial value.
+    ///
+    /// `R0` always holds zero: writes to it are ignored, and the
+    /// instructions reading it never wait for it.
+    pub fn new(size: usize) -> IntegerUnit {
+        IntegerUnit {
+            inner: (0..size)
+                .map(|v| IntegerUnitInner {
+                    qi: None,
+                    value: Some(match v {
+                        0 => value::new(ValueInner::Imm(0)),
+                        v => value::new(Unit::Regs(RegId(v as u8)).into()),
+                    }),
+                })
+                .collect(),
+        }
+    }
+
+    /// Create the register file with every register set to zero.
+    pub fn zeroed(size: usize) -> IntegerUnit {
+        IntegerUnit {
+            inner: (0..size)
+                .map(|_| IntegerUnitInner {
+                    qi: None,
+                    value: Some(value::new(ValueInner::Imm(0))),
+                })
+                .collect(),
+        }
+    }
+
+    /// Whether the register file has a register.
+    #[inline]
+    pub fn contains(&self, id: RegId) -> bool {
+        (id.0 as usize) < self.inner.len()
+    }
+
+    /// Set the initial value of a register, except `R0`.
+    pub fn set(&mut self, id: RegId, val: i64) {
+        if id.is_zero() {
+            return;
+        }
+        self.inner[id.0 as usize].value = Some(value::new(ValueInner::Imm(val)));
+    }
+
+    /// Mark the integer unit as busy.
+    ///
+    /// With a reorder buffer the register keeps its committed
+    /// value until the new result is committed.
+    pub fn mark_busy(&mut self, id: RegId, qi: Tag) {
+        if id.is_zero() {
+            return;
+        }
+        let reg = &mut self.inner[id.0 as usize];
+        reg.qi = Some(qi);
+        if let Tag::Rs(_) = qi {
+            reg.value = None;
+        }
+    }
+
+    /// Mark the integer unit as ready.
+    pub fn mark_ready(&mut self, id: RegId, qi: Tag, value: Value) {
+        let reg = &mut self.inner[id.0 as usize];
+        if reg.qi == Some(qi) {
+            reg.value = Some(value);
+        }
+    }
+
+    /// Commit the value of a reorder buffer entry.
+    pub fn commit(&mut self, id: RegId, qi: RobId, value: Value) {
+        if id.is_zero() {
+            return;
+        }
+        let reg = &mut self.inner[id.0 as usize];
+        reg.value = Some(value);
+        if reg.qi == Some(Tag::Rob(qi)) {
+            reg.qi = None;
+        }
+    }
+
+    /// Forget the pending results of the reorder buffer,
+    /// after every speculative instruction has been squashed.
+    pub fn squash(&mut self) {
+        for reg in self.inner.iter_mut() {
+            if let Some(Tag::Rob(_)) = reg.qi {
+                reg.qi = None;
+            }
+        }
+    }
+
+    pub fn get(&self, id: RegId) -> &IntegerUnitInner {
+        &self.inner[id.0 as usize]
+    }
+
+    /// Whether any register is waiting for or holds a renamed result.
+    pub fn is_renamed(&self) -> bool {
+        self.inner.iter().any(|reg| reg.qi.is_some())
+    }
+
+    pub fn clear(&mut self) {
+        for reg in self.inner.iter_mut() {
+            reg.qi.take();
+        }
+    }
+}
+
+impl std::fmt::Debug for IntegerUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (i, reg) in self.inner.iter().enumerate() {
+            let Some(qi) = reg.qi else {
+                continue;
+            };
+            let value = match &reg.value {
+                Some(value) => style(format!("{value}")).cyan().underlined(),
+                None => style("None".to_string()).white(),
+            };
+            writeln!(
+                f,
+                "{} : {} -> {}",
+                style(RegId(i as u8)).magenta().underlined(),
+                qi,
+                value
+            )?;
+        }
+        Ok(())
+    }
 }
 
 impl std::fmt::Display for RegId {
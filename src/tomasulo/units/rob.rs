@@ -0,0 +1,174 @@
+use std::collections::VecDeque;
+
+use console::style;
+
+use crate::tomasulo::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RobId(u8);
+
+impl RobId {
+    /// The name of the entry, such as `ROB0`, without colors.
+    pub fn name(&self) -> String {
+        format!("ROB{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum RobState {
+    /// The instruction is waiting in or executing from a reservation station.
+    Issued,
+    /// The result has been written and the instruction can be committed.
+    Written,
+}
+
+#[derive(Debug, Clone)]
+pub struct RobEntry {
+    pub id: RobId,
+    pub state: RobState,
+    pub inst: Instruction,
+    pub value: Option<Value>,
+    /// The memory address written by a store.
+    pub addr: Option<Value>,
+}
+
+/// The reorder buffer.
+///
+/// Entries are allocated in program order at issue and
+/// leave the buffer in the same order when they are committed.
+pub struct ReorderBuffer {
+    size: usize,
+    next: usize,
+    entries: VecDeque<RobEntry>,
+}
+
+impl ReorderBuffer {
+    pub fn new(size: usize) -> ReorderBuffer {
+        assert!(size > 0 && size <= u8::MAX as usize);
+        ReorderBuffer {
+            size,
+            next: 0,
+            entries: VecDeque::with_capacity(size),
+        }
+    }
+
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.entries.len() == self.size
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Allocate an entry at the tail for an issued instruction.
+    pub fn push(&mut self, inst: Instruction) -> Option<RobId> {
+        if self.is_full() {
+            return None;
+        }
+        let id = RobId(self.next as u8);
+        self.next = (self.next + 1) % self.size;
+        self.entries.push_back(RobEntry {
+            id,
+            state: RobState::Issued,
+            inst,
+            value: None,
+            addr: None,
+        });
+        Some(id)
+    }
+
+    /// Iterate over the entries from head to tail.
+    pub fn iter(&self) -> impl Iterator<Item = &RobEntry> {
+        self.entries.iter()
+    }
+
+    pub fn get(&self, id: RobId) -> Option<&RobEntry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    /// Get the value of an entry if its result has been written.
+    pub fn value(&self, id: RobId) -> Option<Value> {
+        self.get(id)
+            .filter(|e| e.state == RobState::Written)
+            .and_then(|e| e.value.clone())
+    }
+
+    /// Record the result of an entry written on the CDB.
+    pub fn write(&mut self, id: RobId, inst: Instruction, value: Value, addr: Option<Value>) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.id == id) {
+            entry.inst = inst;
+            entry.addr = addr;
+            entry.value.replace(value);
+            entry.state = RobState::Written;
+        }
+    }
+
+    /// Remove the head entry if it is ready to commit.
+    pub fn commit(&mut self) -> Option<RobEntry> {
+        match self.entries.front() {
+            Some(entry) if entry.state == RobState::Written => self.entries.pop_front(),
+            _ => None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.next = 0;
+    }
+}
+
+impl std::fmt::Debug for ReorderBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for entry in self.entries.iter() {
+            writeln!(f, "{entry}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for RobEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let inst = self.inst.text();
+        let dest = match (self.inst.op, self.inst.dest, &self.addr) {
+            (Type::SD, _, Some(addr)) => format!("M[{addr}]"),
+            (Type::SD, _, None) => String::from("None"),
+            (_, Some(dest), _) => format!("{dest}"),
+            (_, None, _) => String::from("None"),
+        };
+        let value = match &self.value {
+            Some(value) => style(format!("{value}")).cyan(),
+            None => style("None".to_string()).white(),
+        };
+        write!(
+            f,
+            "{} : {},{:<20},{} -> {}",
+            self.id,
+            self.state,
+            style(inst).white().bold(),
+            dest,
+            value
+        )
+    }
+}
+
+impl std::fmt::Display for RobId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:<6}", style(self.name()).green().bold())
+    }
+}
+
+impl std::fmt::Display for RobState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RobState::Issued => write!(f, "{:<6}", style("Issue").red().bold()),
+            RobState::Written => write!(f, "{:<6}", style("Write").green().bold()),
+        }
+    }
+}
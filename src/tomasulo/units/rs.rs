@@ -1,17 +1,25 @@
 use std::collections::BTreeMap;
+use std::str::FromStr;
 
 use console::style;
 
 use crate::tomasulo::*;
 
-const ADD_RS_COUNT: usize = 3;
-const MULT_RS_COUNT: usize = 2;
-const LOAD_BUFFER_COUNT: usize = 3;
-const STORE_BUFFER_COUNT: usize = 3;
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct RsId(RsType, u8);
 
+impl RsId {
+    #[inline]
+    pub fn rs_type(&self) -> RsType {
+        self.0
+    }
+
+    /// The name of the station, such as `ADD0`, without colors.
+    pub fn name(&self) -> String {
+        format!("{:?}{}", self.0, self.1)
+    }
+}
+
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum RsType {
@@ -19,9 +27,11 @@ pub enum RsType {
     MULT,
     LOAD,
     STORE,
+    BRANCH,
+    INT,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum RsState {
     Busy,
     Free,
@@ -29,55 +39,55 @@ pub enum RsState {
     Ready,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RsInner {
     pub id: RsId,
     pub state: RsState,
     inst: Option<Instruction>,
 
-    vj: Option<Value>,
-    vk: Option<Value>,
-    qj: Option<RsId>,
-    qk: Option<RsId>,
+    pub vj: Option<Value>,
+    pub vk: Option<Value>,
+    pub qj: Option<Tag>,
+    pub qk: Option<Tag>,
 
+    /// The reorder buffer entry receiving the result.
+    pub rob: Option<RobId>,
     pub addr: Option<Value>,
     pub result: Option<Value>,
+    /// The value a load takes from an earlier store instead of the memory.
+    pub forwarded: Option<Value>,
 }
 
 pub struct ReservationStation {
     /// use BTreeMap to make the order of
     /// reservation stations deterministic.
     inner: BTreeMap<RsId, RsInner>,
+    mode: EvalMode,
+    mem_size: Option<i64>,
+    mem_align: i64,
 }
 
 impl ReservationStation {
-    pub fn new() -> ReservationStation {
+    pub fn new(config: &MachineConfig) -> ReservationStation {
         let mut inner = BTreeMap::new();
-        for i in 0..ADD_RS_COUNT {
-            inner.insert(
-                RsId(RsType::ADD, i as u8),
-                RsInner::new(RsType::ADD, i as u8),
-            );
-        }
-        for i in 0..MULT_RS_COUNT {
-            inner.insert(
-                RsId(RsType::MULT, i as u8),
-                RsInner::new(RsType::MULT, i as u8),
-            );
-        }
-        for i in 0..LOAD_BUFFER_COUNT {
-            inner.insert(
-                RsId(RsType::LOAD, i as u8),
-                RsInner::new(RsType::LOAD, i as u8),
-            );
+        for rs_type in [
+            RsType::ADD,
+            RsType::MULT,
+            RsType::LOAD,
+            RsType::STORE,
+            RsType::BRANCH,
+            RsType::INT,
+        ] {
+            for i in 0..config.rs_count(rs_type) {
+                inner.insert(RsId(rs_type, i as u8), RsInner::new(rs_type, i as u8));
+            }
         }
-        for i in 0..STORE_BUFFER_COUNT {
-            inner.insert(
-                RsId(RsType::STORE, i as u8),
-                RsInner::new(RsType::STORE, i as u8),
-            );
+        ReservationStation {
+            inner,
+            mode: config.eval_mode(),
+            mem_size: config.mem_size(),
+            mem_align: config.mem_align(),
         }
-        ReservationStation { inner }
     }
 
     /// Get a free reservation station.
@@ -90,6 +100,11 @@ impl ReservationStation {
         None
     }
 
+    /// Iterate over the reservation stations.
+    pub fn iter(&self) -> impl Iterator<Item = &RsInner> {
+        self.inner.values()
+    }
+
     /// Get a reservation station by id.
     #[inline]
     pub fn get_mut(&mut self, id: RsId) -> Option<&mut RsInner> {
@@ -98,9 +113,18 @@ impl ReservationStation {
 
     /// Flush the reservation station with a broadcast value.
     #[inline]
-    pub fn flush(&mut self, rs_id: RsId, value: Value) {
+    pub fn flush(&mut self, tag: Tag, value: Value) {
         for inner in self.inner.values_mut() {
-            inner.flsuh(rs_id, &value);
+            inner.flsuh(tag, &value);
+        }
+    }
+
+    /// Clear the reservation stations of speculative instructions.
+    pub fn squash(&mut self) {
+        for inner in self.inner.values_mut() {
+            if inner.rob.is_some() {
+                inner.clear();
+            }
         }
     }
 
@@ -112,18 +136,35 @@ impl ReservationStation {
         }
     }
 
-    /// Execute the reservation station.
-    pub fn exec(&mut self, cycle: u64) -> Vec<RsId> {
-        let mut ready = Vec::new();
+    /// Execute the reservation station, starting the instructions
+    /// which get an execution unit and are not `blocked`.
+    ///
+    /// Returns the stations which wait for a free execution unit.
+    pub fn exec(
+        &mut self,
+        cycle: u64,
+        mem: &Memory,
+        units: &mut ExecUnits,
+        blocked: &[RsId],
+    ) -> Vec<RsId> {
+        let (start, delayed) = units.dispatch(cycle, self, blocked);
         for inner in self.inner.values_mut() {
+            let started = inner
+                .inst
+                .as_ref()
+                .is_some_and(|inst| inst.start_cycle.is_some());
             if inner.state == RsState::Busy && inner.is_ready() {
                 inner.state = RsState::Calculating;
-            } else if inner.state == RsState::Calculating && inner.exec(cycle) == RsState::Ready {
-                ready.push(inner.id);
+            } else if inner.state == RsState::Calculating
+                && (started || start.contains(&inner.id))
+                && inner.exec(cycle, mem, self.mode) == RsState::Ready
+            {
                 inner.state = RsState::Ready;
+                let exception = inner.fault(self.mem_size, self.mem_align);
+                inner.inst.as_mut().unwrap().exception = exception;
             }
         }
-        ready
+        delayed
     }
 }
 
@@ -137,78 +178,54 @@ impl RsInner {
             vk: None,
             qj: None,
             qk: None,
+            rob: None,
             addr: None,
             result: None,
+            forwarded: None,
         }
     }
 
-    /// Apply an instruction to the reservation station.
-    pub fn apply(&mut self, mut inst: Instruction, fu: &FloatingUnit, cycle: u64) {
-        inst.emit(cycle);
+    /// The tag under which the result is broadcast.
+    #[inline]
+    pub fn tag(&self) -> Tag {
+        match self.rob {
+            Some(rob_id) => Tag::Rob(rob_id),
+            None => Tag::Rs(self.id),
+        }
+    }
 
+    /// Apply an instruction to the reservation station.
+    pub fn apply(
+        &mut self,
+        mut inst: Instruction,
+        fu: &FloatingUnit,
+        int: &IntegerUnit,
+        rob: Option<&ReorderBuffer>,
+        cycle: u64,
+        latency: u64,
+    ) {
+        inst.emit(cycle, latency);
+
+        let src1 = inst.src1.as_ref().unwrap();
+        let src2 = inst.src2.as_ref().unwrap();
         match inst.op {
             Type::LD => {
-                // assume that the address is always ready
-                self.addr.replace(inst.src1.clone().unwrap());
-                self.vk.replace(inst.src2.clone().unwrap());
+                // the address is the offset plus the base register
+                self.addr.replace(src1.clone());
+                (self.vk, self.qk) = read(src2, fu, int, rob);
             }
             Type::SD => {
-                // assume that the address is always ready
-                self.addr.replace(inst.src1.clone().unwrap());
-                self.vj.replace(inst.src2.clone().unwrap());
+                self.addr.replace(src1.clone());
+                (self.vj, self.qj) = read(src2, fu, int, rob);
 
                 // the dest of SD is the value to be stored
-                if let Unit::Fu(fuid) = inst.dest {
-                    let fu = fu.get(fuid);
-                    match &fu.value {
-                        Some(value) => {
-                            self.vk.replace(value.clone());
-                        }
-                        None => {
-                            self.qk = fu.qi;
-                        }
-                    }
+                if let Some(dest) = inst.dest {
+                    (self.vk, self.qk) = read(&value::new(dest.into()), fu, int, rob);
                 }
             }
             _ => {
-                // use fu to calculate the value
-                if let Some(src1) = inst.src1.clone() {
-                    match *src1 {
-                        ValueInner::Unit(Unit::Fu(fuid)) => {
-                            let fu = fu.get(fuid);
-                            match &fu.value {
-                                Some(value) => {
-                                    self.vj.replace(value.clone());
-                                }
-                                None => {
-                                    self.qj = fu.qi;
-                                }
-                            }
-                        }
-                        _ => {
-                            panic!("src1 is not a fu: {src1:?}")
-                        }
-                    }
-                }
-
-                if let Some(src2) = inst.src2.clone() {
-                    match *src2 {
-                        ValueInner::Unit(Unit::Fu(fuid)) => {
-                            let fu = fu.get(fuid);
-                            match &fu.value {
-                                Some(value) => {
-                                    self.vk.replace(value.clone());
-                                }
-                                None => {
-                                    self.qk = fu.qi;
-                                }
-                            }
-                        }
-                        _ => {
-                            panic!("src2 is not a fu: {src2:?}")
-                        }
-                    }
-                }
+                (self.vj, self.qj) = read(src1, fu, int, rob);
+                (self.vk, self.qk) = read(src2, fu, int, rob);
             }
         }
 
@@ -223,19 +240,31 @@ impl RsInner {
         self.vk = None;
         self.qj = None;
         self.qk = None;
+        self.rob = None;
         self.addr = None;
+        self.forwarded = None;
     }
 
     pub fn is_ready(&self) -> bool {
         match self.inst.as_ref().unwrap().op {
-            Type::LD | Type::SD => self.vk.is_some(),
+            Type::LD => self.vk.is_some(),
             _ => self.vj.is_some() && self.vk.is_some(),
         }
     }
 
+    #[inline]
+    pub fn inst(&self) -> Option<&Instruction> {
+        self.inst.as_ref()
+    }
+
+    #[inline]
+    pub fn inst_mut(&mut self) -> Option<&mut Instruction> {
+        self.inst.as_mut()
+    }
+
     #[inline]
     pub fn dest(&self) -> Option<&Unit> {
-        self.inst.as_ref().map(|inst| &inst.dest)
+        self.inst.as_ref().and_then(|inst| inst.dest.as_ref())
     }
 
     #[inline]
@@ -244,21 +273,51 @@ impl RsInner {
     }
 
     /// Execute the reservation station.
-    pub fn exec(&mut self, cycle: u64) -> RsState {
+    ///
+    /// Loads read the memory when they finish executing.
+    pub fn exec(&mut self, cycle: u64, mem: &Memory, mode: EvalMode) -> RsState {
         if let Some(inst) = self.inst.as_mut() {
             let op = inst.op;
             if inst.exec(cycle) {
                 self.result.replace(match op {
-                    Type::LD | Type::SD => {
+                    Type::LD if self.forwarded.is_some() => self.forwarded.clone().unwrap(),
+                    Type::LD => {
                         let addr = self.addr.as_ref().unwrap();
                         let vk = self.vk.as_ref().unwrap();
-                        let addr = value::apply_op(Type::ADDD, addr.clone(), vk.clone());
-                        value::new(ValueInner::MemAddr(addr))
+                        let addr = value::apply_op(Type::ADD, addr.clone(), vk.clone(), mode);
+                        match (mode, addr.eval()) {
+                            (EvalMode::Numeric, Some(addr)) => {
+                                value::new(ValueInner::Float(mem.read(addr as i64)))
+                            }
+                            _ => value::new(ValueInner::MemAddr(addr)),
+                        }
+                    }
+                    Type::SD => {
+                        // keep the effective address for writing the memory
+                        let addr = self.addr.as_ref().unwrap();
+                        let vj = self.vj.as_ref().unwrap();
+                        self.addr.replace(value::apply_op(
+                            Type::ADD,
+                            addr.clone(),
+                            vj.clone(),
+                            mode,
+                        ));
+                        // the result of SD is the value to be stored
+                        self.vk.clone().unwrap()
+                    }
+                    _ if op.is_branch() => {
+                        let lhs = self.vj.as_ref().unwrap();
+                        let rhs = self.vk.as_ref().unwrap();
+                        // an undecided condition is kept as an expression
+                        match value::compare(op, lhs, rhs) {
+                            Some(taken) => value::new(ValueInner::Imm(taken as i64)),
+                            None => value::new(ValueInner::Op(op, lhs.clone(), rhs.clone())),
+                        }
                     }
                     _ => {
                         let lhs = self.vj.as_ref().unwrap();
                         let rhs = self.vk.as_ref().unwrap();
-                        value::apply_op(op, lhs.clone(), rhs.clone())
+                        value::apply_op(op, lhs.clone(), rhs.clone(), mode)
                     }
                 });
                 RsState::Ready
@@ -270,21 +329,48 @@ impl RsInner {
         }
     }
 
+    /// The exception raised by the completed instruction, if any.
+    ///
+    /// Divisions by zero fault, and so do loads and stores to an
+    /// address which is misaligned or out of the memory. Only operands
+    /// which evaluate to a number are checked, so in symbolic mode an
+    /// expression such as a loaded value never faults.
+    fn fault(&self, mem_size: Option<i64>, align: i64) -> Option<Exception> {
+        let inst = self.inst.as_ref()?;
+        let addr = match inst.op {
+            Type::DIVD => {
+                let divisor = self.vk.as_ref()?.eval()?;
+                return (divisor == 0.0).then_some(Exception::DivideByZero);
+            }
+            Type::LD => self.addr.as_ref()?.eval()? + self.vk.as_ref()?.eval()?,
+            // the address of SD is already the effective one
+            Type::SD => self.addr.as_ref()?.eval()?,
+            _ => return None,
+        } as i64;
+        if addr.rem_euclid(align) != 0 {
+            Some(Exception::MisalignedAddress(addr))
+        } else if mem_size.is_some_and(|size| addr < 0 || addr >= size) {
+            Some(Exception::AddressOutOfRange(addr))
+        } else {
+            None
+        }
+    }
+
     /// Flush the reservation station.
     ///
     /// This will fill the value of the reservation station
     /// if the value has been calculated.
-    pub fn flsuh(&mut self, rs_id: RsId, value: &Value) {
+    pub fn flsuh(&mut self, tag: Tag, value: &Value) {
         if self.state == RsState::Busy && !self.is_ready() {
             if let Some(qj) = self.qj {
-                if qj == rs_id {
+                if qj == tag {
                     self.vj.replace(value.clone());
                     self.qj = None;
                 }
             }
 
             if let Some(qk) = self.qk {
-                if qk == rs_id {
+                if qk == tag {
                     self.vk.replace(value.clone());
                     self.qk = None;
                 }
@@ -302,6 +388,32 @@ impl RsInner {
     }
 }
 
+/// Read an operand of an issued instruction.
+///
+/// Returns the value if it is available, otherwise the tag of the
+/// pending result. With a reorder buffer, a result which has been
+/// written but not yet committed is read from the buffer.
+fn read(
+    src: &Value,
+    fu: &FloatingUnit,
+    int: &IntegerUnit,
+    rob: Option<&ReorderBuffer>,
+) -> (Option<Value>, Option<Tag>) {
+    let (value, qi) = match **src {
+        ValueInner::Unit(Unit::Fu(id)) => (&fu.get(id).value, fu.get(id).qi),
+        ValueInner::Unit(Unit::Regs(id)) => (&int.get(id).value, int.get(id).qi),
+        _ => return (Some(src.clone()), None),
+    };
+    match (value, qi) {
+        (_, Some(Tag::Rob(rob_id))) => match rob.and_then(|rob| rob.value(rob_id)) {
+            Some(value) => (Some(value), None),
+            None => (None, qi),
+        },
+        (Some(value), _) => (Some(value.clone()), None),
+        (None, qi) => (None, qi),
+    }
+}
+
 impl From<Type> for RsType {
     fn from(t: Type) -> RsType {
         match t {
@@ -309,6 +421,26 @@ impl From<Type> for RsType {
             Type::MULTD | Type::DIVD => RsType::MULT,
             Type::LD => RsType::LOAD,
             Type::SD => RsType::STORE,
+            Type::BEQ | Type::BNE | Type::BEQZ | Type::BNEZ => RsType::BRANCH,
+            Type::ADD | Type::SUB | Type::ADDI | Type::SUBI | Type::DADDI | Type::DADDUI => {
+                RsType::INT
+            }
+        }
+    }
+}
+
+impl FromStr for RsType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "ADD" => Ok(RsType::ADD),
+            "MULT" => Ok(RsType::MULT),
+            "LOAD" => Ok(RsType::LOAD),
+            "STORE" => Ok(RsType::STORE),
+            "BRANCH" => Ok(RsType::BRANCH),
+            "INT" => Ok(RsType::INT),
+            _ => Err(()),
         }
     }
 }
@@ -359,11 +491,7 @@ impl std::fmt::Display for RsInner {
 
 impl std::fmt::Display for RsId {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(
-            f,
-            "{:<6}",
-            style(format!("{:?}{}", self.0, self.1)).green().bold()
-        )
+        write!(f, "{:<6}", style(self.name()).green().bold())
     }
 }
 
@@ -1,4 +1,5 @@
 use std::rc::Rc;
+use std::str::FromStr;
 
 use super::*;
 
@@ -18,14 +19,68 @@ pub enum ValueInner {
     Op(Type, Value, Value),
 }
 
+/// How the results of operations are computed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvalMode {
+    /// Build an expression of the operands, like `(M[34+R2]*F4)`.
+    #[default]
+    Symbolic,
+    /// Compute the number, with integer registers and memory starting at zero.
+    Numeric,
+}
+
 pub fn new(inner: ValueInner) -> Value {
     Rc::new(inner)
 }
 
-pub fn apply_op(t: Type, v1: Value, v2: Value) -> Value {
+pub fn apply_op(t: Type, v1: Value, v2: Value, mode: EvalMode) -> Value {
+    if mode == EvalMode::Numeric {
+        if t.is_int() {
+            if let (Some(lhs), Some(rhs)) = (v1.eval_int(), v2.eval_int()) {
+                if let Some(v) = calc_int(t, lhs, rhs) {
+                    return new(ValueInner::Imm(v));
+                }
+            }
+        } else if let (Some(lhs), Some(rhs)) = (v1.eval(), v2.eval()) {
+            if let Some(v) = calc(t, lhs, rhs) {
+                return new(ValueInner::Float(v));
+            }
+        }
+    }
     new(ValueInner::Op(t, v1, v2))
 }
 
+fn calc(t: Type, v1: f64, v2: f64) -> Option<f64> {
+    match t {
+        Type::ADDD => Some(v1 + v2),
+        Type::SUBD => Some(v1 - v2),
+        Type::MULTD => Some(v1 * v2),
+        Type::DIVD => Some(v1 / v2),
+        _ => None,
+    }
+}
+
+/// Compute an integer operation, wrapping around on overflow
+/// like the 64-bit registers.
+fn calc_int(t: Type, v1: i64, v2: i64) -> Option<i64> {
+    match t {
+        Type::ADD | Type::ADDI | Type::DADDI | Type::DADDUI => Some(v1.wrapping_add(v2)),
+        Type::SUB | Type::SUBI => Some(v1.wrapping_sub(v2)),
+        _ => None,
+    }
+}
+
+/// Evaluate the condition of a branch, `None` if an operand can not
+/// be evaluated, such as a value loaded from memory in symbolic mode.
+pub fn compare(t: Type, v1: &Value, v2: &Value) -> Option<bool> {
+    let (v1, v2) = (v1.eval()?, v2.eval()?);
+    match t {
+        Type::BEQ | Type::BEQZ => Some(v1 == v2),
+        Type::BNE | Type::BNEZ => Some(v1 != v2),
+        _ => None,
+    }
+}
+
 impl From<i64> for ValueInner {
     fn from(v: i64) -> ValueInner {
         ValueInner::Imm(v)
@@ -63,6 +118,29 @@ impl std::fmt::Display for ValueInner {
 }
 
 impl ValueInner {
+    /// Evaluate the value if it only depends on known numbers.
+    pub fn eval(&self) -> Option<f64> {
+        match self {
+            ValueInner::Imm(v) => Some(*v as f64),
+            ValueInner::Float(v) => Some(*v),
+            ValueInner::Unit(_) | ValueInner::MemAddr(_) => None,
+            ValueInner::Op(t, v1, v2) if t.is_int() => {
+                calc_int(*t, v1.eval_int()?, v2.eval_int()?).map(|v| v as f64)
+            }
+            ValueInner::Op(t, v1, v2) => calc(*t, v1.eval()?, v2.eval()?),
+        }
+    }
+
+    /// Evaluate the value as an integer, computing integer operations
+    /// without rounding through floats.
+    pub fn eval_int(&self) -> Option<i64> {
+        match self {
+            ValueInner::Imm(v) => Some(*v),
+            ValueInner::Op(t, v1, v2) if t.is_int() => calc_int(*t, v1.eval_int()?, v2.eval_int()?),
+            _ => self.eval().map(|v| v as i64),
+        }
+    }
+
     pub fn brief(&self) -> String {
         match self {
             ValueInner::Imm(v) => format!("{v}"),
@@ -73,3 +151,37 @@ impl ValueInner {
         }
     }
 }
+
+impl FromStr for EvalMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "symbolic" => Ok(EvalMode::Symbolic),
+            "numeric" => Ok(EvalMode::Numeric),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integer_ops() {
+        let imm = |v: i64| new(ValueInner::Imm(v));
+        // past 2^53 a double can not hold every integer
+        let big = 9_007_199_254_740_993;
+        let sum = apply_op(Type::DADDUI, imm(big), imm(2), EvalMode::Numeric);
+        assert_eq!(*sum, ValueInner::Imm(big + 2));
+        let diff = apply_op(Type::SUB, imm(big), imm(-2), EvalMode::Numeric);
+        assert_eq!(*diff, ValueInner::Imm(big + 2));
+        // and the registers wrap around
+        let max = apply_op(Type::ADDI, imm(i64::MAX), imm(1), EvalMode::Numeric);
+        assert_eq!(*max, ValueInner::Imm(i64::MIN));
+
+        let symbolic = apply_op(Type::DADDI, imm(big), imm(2), EvalMode::Symbolic);
+        assert_eq!(symbolic.eval_int(), Some(big + 2));
+    }
+}